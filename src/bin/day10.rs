@@ -1,9 +1,13 @@
-use std::str::FromStr;
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct ParseInputError(String);
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Command {
     Addx(isize),
     Noop,
@@ -32,6 +36,242 @@ impl FromStr for Command {
     }
 }
 
+impl Command {
+    // Number of cycles the command takes to complete.
+    pub fn cycles(&self) -> usize {
+        match self {
+            Command::Noop => 1,
+            Command::Addx(_) => 2,
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Noop => write!(f, "noop"),
+            Command::Addx(value) => write!(f, "addx {}", value),
+        }
+    }
+}
+
+pub fn parse_program(input: &str) -> Result<Vec<Command>, ParseInputError> {
+    input.lines().map(|line| line.parse::<Command>()).collect()
+}
+
+// A command, with the cycle it starts on and the value of X it sees.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AnnotatedCommand {
+    pub command: Command,
+    pub start_cycle: usize,
+    pub x: isize,
+}
+
+impl Display for AnnotatedCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cycle {:>3} | X = {:>3} | {}",
+            self.start_cycle, self.x, self.command
+        )
+    }
+}
+
+pub fn disassemble(program: &[Command]) -> Vec<AnnotatedCommand> {
+    let mut cpu = Cpu::default();
+    program
+        .iter()
+        .map(|command| {
+            let annotated = AnnotatedCommand {
+                command: *command,
+                start_cycle: cpu.cycle + 1,
+                x: cpu.x,
+            };
+            cpu.execute(command, &mut []);
+            annotated
+        })
+        .collect()
+}
+
+// The pixel at (`x`, `y`) can't be drawn by any program.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AssembleError {
+    pub x: usize,
+    pub y: usize,
+}
+
+// Builds a program that draws exactly the '#' pixels of `target` on a CRT of the same size.
+//
+// The value of X during each cycle has to cover the pixel being drawn when it's lit, and stay away
+// from it otherwise. X can only change at the end of an `addx`, so it holds the same value for at
+// least two cycles. This walks the cycles in order, keeping every value of X that can be reached
+// at each command boundary, and then follows the commands back from the end of the screen.
+pub fn assemble(target: &CRT) -> Result<Vec<Command>, AssembleError> {
+    let num_cycles = target.width * target.height;
+
+    // Values outside this range behave the same as its ends, which never light a pixel.
+    let (min_x, max_x) = (-2, target.width as isize + 1);
+    let num_values = (max_x - min_x + 1) as usize;
+    let index = |x: isize| (x - min_x) as usize;
+    let draws = |cycle: usize, x: isize| {
+        let lit = ((cycle % target.width) as isize - x).abs() <= 1;
+        lit == (target.pixels[cycle] == '#')
+    };
+
+    // For each command boundary and value of X, the command that got there and the value of X
+    // before it. An `addx` finishing on the last cycle can go one past the end of the screen.
+    let mut previous: Vec<Vec<Option<(isize, Command)>>> =
+        vec![vec![None; num_values]; num_cycles + 2];
+    let mut reachable = vec![vec![false; num_values]; num_cycles + 2];
+    reachable[0][index(1)] = true;
+
+    let mut furthest_cycle = 0;
+    for cycle in 0..num_cycles {
+        for x in min_x..=max_x {
+            if !reachable[cycle][index(x)] || !draws(cycle, x) {
+                continue;
+            }
+            furthest_cycle = cycle + 1;
+
+            if !reachable[cycle + 1][index(x)] {
+                reachable[cycle + 1][index(x)] = true;
+                previous[cycle + 1][index(x)] = Some((x, Command::Noop));
+            }
+
+            if cycle + 1 < num_cycles && !draws(cycle + 1, x) {
+                continue;
+            }
+
+            for next_x in min_x..=max_x {
+                if !reachable[cycle + 2][index(next_x)] {
+                    reachable[cycle + 2][index(next_x)] = true;
+                    previous[cycle + 2][index(next_x)] = Some((x, Command::Addx(next_x - x)));
+                }
+            }
+        }
+    }
+
+    let end = [num_cycles, num_cycles + 1].into_iter().find_map(|cycle| {
+        (min_x..=max_x)
+            .find(|x| reachable[cycle][index(*x)])
+            .map(|x| (cycle, x))
+    });
+
+    let Some((mut cycle, mut x)) = end else {
+        return Err(AssembleError {
+            x: furthest_cycle % target.width,
+            y: furthest_cycle / target.width,
+        });
+    };
+
+    let mut program = vec![];
+    while let Some((previous_x, command)) = previous[cycle][index(x)] {
+        program.push(command);
+        cycle -= command.cycles();
+        x = previous_x;
+    }
+    program.reverse();
+    Ok(program)
+}
+
+// Receives the state of the CPU on every cycle. Cycles are numbered starting from 1.
+pub trait CycleObserver {
+    // Called while `cycle` is executing. `x` is the value of the X register during the cycle.
+    fn during_cycle(&mut self, _cycle: usize, _x: isize) {}
+
+    // Called once `cycle` has completed. `x` already includes the result of a command that
+    // finished on this cycle.
+    fn after_cycle(&mut self, _cycle: usize, _x: isize) {}
+}
+
+#[derive(Debug)]
+pub struct Cpu {
+    pub x: isize,
+    pub cycle: usize,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self { x: 1, cycle: 0 }
+    }
+}
+
+impl Cpu {
+    pub fn execute(&mut self, command: &Command, observers: &mut [&mut dyn CycleObserver]) {
+        for remaining in (0..command.cycles()).rev() {
+            self.cycle += 1;
+            for observer in observers.iter_mut() {
+                observer.during_cycle(self.cycle, self.x);
+            }
+
+            if remaining == 0 {
+                if let Command::Addx(value) = command {
+                    self.x += value;
+                }
+            }
+
+            for observer in observers.iter_mut() {
+                observer.after_cycle(self.cycle, self.x);
+            }
+        }
+    }
+
+    pub fn run(&mut self, program: &[Command], observers: &mut [&mut dyn CycleObserver]) {
+        for command in program {
+            self.execute(command, observers);
+        }
+    }
+}
+
+// Sums the signal strength during the 20th cycle and every 40 cycles after that, up to the
+// 220th cycle.
+#[derive(Debug, Default)]
+pub struct SignalStrength {
+    pub sum: isize,
+}
+
+impl CycleObserver for SignalStrength {
+    fn during_cycle(&mut self, cycle: usize, x: isize) {
+        if cycle % 40 == 20 && cycle <= 220 {
+            self.sum += cycle as isize * x;
+        }
+    }
+}
+
+// Width and height of a letter drawn on the CRT. Letters are separated by a blank column.
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+
+// The letters used by Advent of Code puzzles, drawn one row after the other.
+const GLYPHS: &[(char, &str)] = &[
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Y', "#...#....#.#..#...#...#."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    // The CRT is not as tall as a letter.
+    InvalidHeight(usize),
+    // The letter at `index` doesn't match any known glyph.
+    UnknownGlyph { index: usize, glyph: String },
+}
+
 #[derive(Debug)]
 pub struct CRT {
     pub pixels: Vec<char>,
@@ -53,10 +293,46 @@ impl CRT {
         let pos = self.char_pos(x, y);
         self.pixels[pos]
     }
+
+    // Reads the letters drawn on the CRT. Each letter takes a 4x6 cell, followed by a blank
+    // column.
+    pub fn decode_letters(&self) -> Result<String, OcrError> {
+        if self.height != GLYPH_HEIGHT {
+            return Err(OcrError::InvalidHeight(self.height));
+        }
+
+        let num_letters = (self.width + 1) / (GLYPH_WIDTH + 1);
+        (0..num_letters)
+            .map(|index| {
+                let start_x = index * (GLYPH_WIDTH + 1);
+                let glyph = (0..GLYPH_HEIGHT)
+                    .flat_map(|y| (start_x..start_x + GLYPH_WIDTH).map(move |x| (x, y)))
+                    .map(|(x, y)| self.char_at(x, y))
+                    .collect::<String>();
+
+                GLYPHS
+                    .iter()
+                    .find(|(_, pattern)| *pattern == glyph)
+                    .map(|(letter, _)| *letter)
+                    .ok_or(OcrError::UnknownGlyph { index, glyph })
+            })
+            .collect()
+    }
 }
 
-impl ToString for CRT {
-    fn to_string(&self) -> String {
+// Draws a pixel whenever the sprite, centered on X, covers the position being drawn.
+impl CycleObserver for CRT {
+    fn during_cycle(&mut self, cycle: usize, x: isize) {
+        let position = cycle - 1;
+        let (row, col) = (position / self.width, position % self.width);
+        if row < self.height && (col as isize - x).abs() <= 1 {
+            self.set_char_at(col, row, '#');
+        }
+    }
+}
+
+impl Display for CRT {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let mut content = String::with_capacity((self.width + 1) * self.height + 2);
         content.push('\n');
         for y in 0..self.height {
@@ -67,7 +343,7 @@ impl ToString for CRT {
             content.push('\n')
         }
         content.push('\n');
-        content
+        f.write_str(&content)
     }
 }
 
@@ -84,44 +360,201 @@ impl Default for CRT {
 }
 
 fn main() {
+    let program = parse_program(include_str!("day10.txt")).unwrap();
+    // let program = parse_program(include_str!("day10_sample.txt")).unwrap();
+
+    let mut signal_strength = SignalStrength::default();
     let mut crt = CRT::default();
-    let mut cycle = 0;
-    let mut register_x: isize = 1;
-    let mut sum_frequencies = 0;
-
-    for line in include_str!("day10.txt").lines() {
-        // for line in include_str!("day10_sample.txt").lines() {
-        let command = line.parse::<Command>().unwrap();
-        let (cycles, value) = match command {
-            Command::Noop => (1, 0),
-            Command::Addx(value) => (2, value),
-        };
-        for _ in 0..cycles {
-            println!("{} => {}", cycle, register_x);
-            let row = cycle / 40;
-            let col = cycle % 40;
-            if col >= register_x - 1 && col <= register_x + 1 {
-                crt.set_char_at(col as usize, row as usize, '#');
-            }
+    Cpu::default().run(&program, &mut [&mut signal_strength, &mut crt]);
+
+    println!("Sum Frequencies: {}", signal_strength.sum);
+    println!("{}", crt);
+    match crt.decode_letters() {
+        Ok(letters) => println!("Letters: {}", letters),
+        Err(e) => println!("Unable to read letters: {:?}", e),
+    }
+
+    // Pass "--disassemble" to print the program with the cycle and X value of each command.
+    if std::env::args().any(|arg| arg == "--disassemble") {
+        for annotated in disassemble(&program) {
+            println!("{}", annotated);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        during: Vec<isize>,
+        after: Vec<isize>,
+    }
+
+    impl CycleObserver for Recorder {
+        fn during_cycle(&mut self, _cycle: usize, x: isize) {
+            self.during.push(x);
+        }
 
-            cycle += 1;
-            if cycle == 20
-                || cycle == 60
-                || cycle == 100
-                || cycle == 140
-                || cycle == 180
-                || cycle == 220
-            {
-                let frequency = cycle as isize * register_x;
-                sum_frequencies += frequency;
-                println!(
-                    "Cycle: {} / X:{} => Frequency: {}",
-                    cycle, register_x, frequency
-                );
+        fn after_cycle(&mut self, _cycle: usize, x: isize) {
+            self.after.push(x);
+        }
+    }
+
+    fn crt_from_str(s: &str) -> CRT {
+        let lines = s.lines().collect::<Vec<_>>();
+        CRT {
+            pixels: lines.iter().flat_map(|line| line.chars()).collect(),
+            width: lines[0].len(),
+            height: lines.len(),
+        }
+    }
+
+    #[test]
+    fn decodes_letters() {
+        let crt = crt_from_str(
+            "\
+.##..###..#..#.####.###.
+#..#.#..#.#..#.#....#..#
+#..#.###..####.###..#..#
+####.#..#.#..#.#....###.
+#..#.#..#.#..#.#....#.#.
+#..#.###..#..#.####.#..#",
+        );
+        assert_eq!(crt.decode_letters(), Ok("ABHER".to_string()));
+    }
+
+    #[test]
+    fn decodes_input_letters() {
+        let program = parse_program(include_str!("day10.txt")).unwrap();
+        let mut crt = CRT::default();
+        Cpu::default().run(&program, &mut [&mut crt]);
+        assert_eq!(crt.decode_letters(), Ok("FPGPHFGH".to_string()));
+    }
+
+    #[test]
+    fn rejects_unknown_glyphs() {
+        let program = parse_program(include_str!("day10_sample.txt")).unwrap();
+        let mut crt = CRT::default();
+        Cpu::default().run(&program, &mut [&mut crt]);
+        assert_eq!(
+            crt.decode_letters(),
+            Err(OcrError::UnknownGlyph {
+                index: 0,
+                glyph: "##..###.################".to_string(),
+            })
+        );
+
+        let crt = crt_from_str("####\n####");
+        assert_eq!(crt.decode_letters(), Err(OcrError::InvalidHeight(2)));
+    }
+
+    fn draw(program: &[Command]) -> CRT {
+        let mut crt = CRT::default();
+        Cpu::default().run(program, &mut [&mut crt]);
+        crt
+    }
+
+    #[test]
+    fn assembles_input_image() {
+        let target = draw(&parse_program(include_str!("day10.txt")).unwrap());
+        let program = assemble(&target).unwrap();
+        assert_eq!(draw(&program).pixels, target.pixels);
+    }
+
+    #[test]
+    fn assembles_sample_image() {
+        let target = draw(&parse_program(include_str!("day10_sample.txt")).unwrap());
+        let program = assemble(&target).unwrap();
+        assert_eq!(draw(&program).pixels, target.pixels);
+    }
+
+    #[test]
+    fn assembles_letters() {
+        let mut target = CRT::default();
+        let letters = [
+            ".##..###..#..#.####.###.",
+            "#..#.#..#.#..#.#....#..#",
+            "#..#.###..####.###..#..#",
+            "####.#..#.#..#.#....###.",
+            "#..#.#..#.#..#.#....#.#.",
+            "#..#.###..#..#.####.#..#",
+        ];
+        for (y, row) in letters.iter().enumerate() {
+            for (x, char) in row.chars().enumerate() {
+                target.set_char_at(x, y, char);
             }
         }
-        register_x += value;
+
+        // The sprite starts over the first two pixels, so they need to be lit.
+        assert_eq!(assemble(&target), Err(AssembleError { x: 0, y: 0 }));
+
+        target.set_char_at(0, 0, '#');
+        let program = assemble(&target).unwrap();
+        let crt = draw(&program);
+        assert_eq!(crt.pixels, target.pixels);
+    }
+
+    #[test]
+    fn disassembles_program() {
+        let program = parse_program("noop\naddx 3\naddx -5\nnoop").unwrap();
+        let annotated = disassemble(&program)
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            annotated,
+            vec![
+                "cycle   1 | X =   1 | noop",
+                "cycle   2 | X =   1 | addx 3",
+                "cycle   4 | X =   4 | addx -5",
+                "cycle   6 | X =  -1 | noop",
+            ]
+        );
+    }
+
+    #[test]
+    fn displays_commands() {
+        let program = "addx -11\nnoop\naddx 6";
+        let displayed = parse_program(program)
+            .unwrap()
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(displayed.join("\n"), program);
+    }
+
+    #[test]
+    fn runs_small_program() {
+        let program = parse_program("noop\naddx 3\naddx -5").unwrap();
+        let mut recorder = Recorder::default();
+        let mut cpu = Cpu::default();
+        cpu.run(&program, &mut [&mut recorder]);
+        assert_eq!(cpu.cycle, 5);
+        assert_eq!(cpu.x, -1);
+        assert_eq!(recorder.during, vec![1, 1, 1, 4, 4]);
+        assert_eq!(recorder.after, vec![1, 1, 4, 4, -1]);
+    }
+
+    #[test]
+    fn sample_program() {
+        let program = parse_program(include_str!("day10_sample.txt")).unwrap();
+        let mut signal_strength = SignalStrength::default();
+        let mut crt = CRT::default();
+        Cpu::default().run(&program, &mut [&mut signal_strength, &mut crt]);
+        assert_eq!(signal_strength.sum, 13140);
+        assert_eq!(
+            crt.to_string(),
+            "
+##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######.....
+
+"
+        );
     }
-    println!("Sum Frequencies: {sum_frequencies}");
-    println!("{}", crt.to_string());
 }
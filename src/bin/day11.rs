@@ -1,17 +1,22 @@
-use std::str::FromStr;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    hash::Hash,
+    str::FromStr,
+};
 
 type Item = u64;
 
-#[derive(Debug)]
-pub struct ParseInputError;
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseInputError(pub String);
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Operand {
     Old,
     Value(Item),
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Operator {
     Sum,
     Mult,
@@ -31,7 +36,7 @@ impl Operator {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Monkey {
     pub items: Vec<Item>,
     pub operation: (Operator, Operand),
@@ -40,60 +45,119 @@ pub struct Monkey {
     pub monkey2: usize,
 }
 
+// Returns what follows `header:` in the line, or an error if the line has a different header.
+fn section<'a>(line: Option<&'a str>, header: &str) -> Result<&'a str, ParseInputError> {
+    let line = line.ok_or_else(|| ParseInputError(format!("Missing \"{}\" section", header)))?;
+    line.trim()
+        .strip_prefix(header)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .map(str::trim)
+        .ok_or_else(|| {
+            ParseInputError(format!(
+                "Expected \"{}\" section, found \"{}\"",
+                header,
+                line.trim()
+            ))
+        })
+}
+
+fn parse_number<T: FromStr>(value: &str, context: &str) -> Result<T, ParseInputError> {
+    value
+        .parse::<T>()
+        .map_err(|_| ParseInputError(format!("Invalid number \"{}\" in {}", value, context)))
+}
+
+fn parse_operand(value: &str) -> Result<Operand, ParseInputError> {
+    match value {
+        "old" => Ok(Operand::Old),
+        _ => Ok(Operand::Value(parse_number(value, "Operation")?)),
+    }
+}
+
 impl FromStr for Monkey {
     type Err = ParseInputError;
 
+    // Parses the notes for one monkey. Lines may end with either "\n" or "\r\n".
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let lines: Vec<&str> = s.split("\r\n").collect();
+        let mut lines = s.lines().filter(|line| !line.trim().is_empty());
+
+        let header = lines.next().unwrap_or_default().trim();
+        let id = header
+            .strip_prefix("Monkey ")
+            .and_then(|rest| rest.strip_suffix(':'))
+            .ok_or_else(|| ParseInputError(format!("Invalid monkey header \"{}\"", header)))?;
+        parse_number::<usize>(id, "monkey header")?;
 
-        // Parse items starting items.
-        let items = lines[1].split(':').next_back().unwrap();
+        // Parse starting items. A monkey may start without any items.
+        let items = section(lines.next(), "Starting items")?;
         let items = items
             .split(',')
-            .map(|f| f.trim().parse().unwrap())
-            .collect::<Vec<_>>();
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| parse_number(item, "Starting items"))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Parse Operation.
-        let parts = lines[2]
-            .split('=')
-            .next_back()
-            .unwrap()
-            .split(' ')
-            .collect::<Vec<_>>();
-        let operator = match parts[2] {
+        // Parse operation, in the form "new = <operand> <operator> <operand>".
+        let operation = section(lines.next(), "Operation")?;
+        let expression = operation.strip_prefix("new =").ok_or_else(|| {
+            ParseInputError(format!("Operation must assign to new: \"{}\"", operation))
+        })?;
+        let parts = expression.split_whitespace().collect::<Vec<_>>();
+        let [left, operator, right] = parts[..] else {
+            return Err(ParseInputError(format!(
+                "Invalid operation \"{}\"",
+                operation
+            )));
+        };
+
+        let operator = match operator {
             "*" => Operator::Mult,
             "+" => Operator::Sum,
-            _ => return Err(ParseInputError),
+            _ => {
+                return Err(ParseInputError(format!(
+                    "Unknown operator \"{}\" in Operation",
+                    operator
+                )))
+            }
         };
 
-        // Parse Operand.
-        let operand = if parts[3] == "old" {
-            Operand::Old
-        } else {
-            Operand::Value(parts[3].parse().unwrap())
+        // Both operators are commutative, so "old" can be on either side.
+        let operand = match (parse_operand(left)?, parse_operand(right)?) {
+            (Operand::Old, operand) | (operand, Operand::Old) => operand,
+            _ => {
+                return Err(ParseInputError(format!(
+                    "Operation must use the old value: \"{}\"",
+                    operation
+                )))
+            }
         };
 
-        // Parse Divisible by
-        let divisible_by = lines[3]
-            .split(' ')
-            .next_back()
-            .ok_or(ParseInputError)?
-            .parse()
-            .map_err(|_| ParseInputError)?;
-
-        let monkey1 = lines[4]
-            .split(' ')
-            .next_back()
-            .ok_or(ParseInputError)?
-            .parse::<usize>()
-            .map_err(|_| ParseInputError)?;
-
-        let monkey2 = lines[5]
-            .split(' ')
-            .next_back()
-            .ok_or(ParseInputError)?
-            .parse::<usize>()
-            .map_err(|_| ParseInputError)?;
+        // Parse test.
+        let test = section(lines.next(), "Test")?;
+        let divisible_by = test
+            .strip_prefix("divisible by ")
+            .ok_or_else(|| ParseInputError(format!("Invalid test \"{}\"", test)))?;
+        let divisible_by = parse_number::<Item>(divisible_by, "Test")?;
+        if divisible_by == 0 {
+            return Err(ParseInputError("Test can't be divisible by 0".to_string()));
+        }
+
+        let throw_target = |header: &str, line: Option<&str>| {
+            let target = section(line, header)?;
+            let target = target.strip_prefix("throw to monkey ").ok_or_else(|| {
+                ParseInputError(format!("Invalid \"{}\" target \"{}\"", header, target))
+            })?;
+            parse_number::<usize>(target, header)
+        };
+        let monkey1 = throw_target("If true", lines.next())?;
+        let monkey2 = throw_target("If false", lines.next())?;
+
+        if let Some(line) = lines.next() {
+            return Err(ParseInputError(format!(
+                "Unexpected line \"{}\"",
+                line.trim()
+            )));
+        }
 
         Ok(Monkey {
             items,
@@ -105,59 +169,700 @@ impl FromStr for Monkey {
     }
 }
 
-fn main() {
-    let input = include_str!("day11_sample.txt");
-    // let input = include_str!("day11.txt");
-
-    let mut monkeys: Vec<Monkey> = vec![];
-    input.split("\r\n\r\n").for_each(|monkey| {
-        let monkey: Monkey = monkey.parse().unwrap();
-        println!("{:?}", monkey);
-        monkeys.push(monkey);
-    });
+// Parses the notes for all the monkeys, separated by blank lines. Also checks that every monkey
+// throws to a monkey that exists.
+pub fn parse_monkeys(input: &str) -> Result<Vec<Monkey>, ParseInputError> {
+    let mut blocks = vec![];
+    let mut current = vec![];
+    for line in input.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(current.join("\n"));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
 
-    let div = monkeys
+    let monkeys = blocks
         .iter()
-        .map(|m| m.divisible_by)
-        .fold(1_u64, |acc, f| acc * f);
-
-    let mut inspections = vec![0_usize; monkeys.len()];
-
-    let part2 = false;
-    let iterations = if part2 { 10000 } else { 20 };
-
-    for round in 1..=iterations {
-        // Executes one round.
-        for i in 0..monkeys.len() {
-            while let Some(item) = monkeys[i].items.pop() {
-                inspections[i] += 1;
-                let (operator, operand) = &monkeys[i].operation;
-                let item = if part2 {
-                    operator.apply(&item, operand) % div
-                } else {
-                    operator.apply(&item, operand) / 3
-                };
-                let divisable = &item % monkeys[i].divisible_by == 0;
-
-                let target_id = if divisable {
-                    monkeys[i].monkey1
-                } else {
-                    monkeys[i].monkey2
-                };
-                monkeys[target_id].items.push(item);
+        .enumerate()
+        .map(|(i, block)| {
+            block
+                .parse::<Monkey>()
+                .map_err(|ParseInputError(e)| ParseInputError(format!("Monkey {}: {}", i, e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (i, monkey) in monkeys.iter().enumerate() {
+        for target in [monkey.monkey1, monkey.monkey2] {
+            if target >= monkeys.len() {
+                return Err(ParseInputError(format!(
+                    "Monkey {}: throws to monkey {}, but there are only {} monkeys",
+                    i,
+                    target,
+                    monkeys.len()
+                )));
             }
         }
+    }
+    Ok(monkeys)
+}
+
+// An unsigned integer of any size, used when worry levels are never reduced. Stored as base 2^32
+// digits, least significant first, without leading zeros.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BigWorry(Vec<u32>);
+
+impl BigWorry {
+    pub fn from_item(item: Item) -> Self {
+        let mut result = BigWorry(vec![item as u32, (item >> 32) as u32]);
+        result.trim();
+        result
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    pub fn add(&self, other: &BigWorry) -> BigWorry {
+        let mut digits = Vec::with_capacity(self.0.len().max(other.0.len()) + 1);
+        let mut carry = 0_u64;
+        for i in 0..self.0.len().max(other.0.len()) {
+            let sum = *self.0.get(i).unwrap_or(&0) as u64 + *other.0.get(i).unwrap_or(&0) as u64;
+            let sum = sum + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+
+        let mut result = BigWorry(digits);
+        result.trim();
+        result
+    }
 
-        if round == 1 || round == 20 || round % 1000 == 0 {
-            println!("Round {}", round);
-            for (monkey, inspections) in inspections.iter().enumerate() {
-                println!("\tMonkey: {}, Inspections: {}", monkey, inspections);
+    pub fn mul(&self, other: &BigWorry) -> BigWorry {
+        let mut digits = vec![0_u32; self.0.len() + other.0.len()];
+        for (i, a) in self.0.iter().enumerate() {
+            let mut carry = 0_u64;
+            for (j, b) in other.0.iter().enumerate() {
+                let product = *a as u64 * *b as u64 + digits[i + j] as u64 + carry;
+                digits[i + j] = product as u32;
+                carry = product >> 32;
             }
-            println!();
+            digits[i + other.0.len()] = carry as u32;
         }
+
+        let mut result = BigWorry(digits);
+        result.trim();
+        result
+    }
+
+    pub fn rem(&self, divisor: Item) -> Item {
+        self.0.iter().rev().fold(0, |remainder, digit| {
+            (((remainder as u128) << 32 | *digit as u128) % divisor as u128) as Item
+        })
+    }
+}
+
+impl Display for BigWorry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Repeatedly divide by 10^9, collecting the remainders as the decimal digits.
+        let mut digits = self.0.clone();
+        let mut chunks = vec![];
+        while !digits.is_empty() {
+            let mut remainder = 0_u64;
+            for digit in digits.iter_mut().rev() {
+                let value = remainder << 32 | *digit as u64;
+                *digit = (value / 1_000_000_000) as u32;
+                remainder = value % 1_000_000_000;
+            }
+            chunks.push(remainder);
+            while digits.last() == Some(&0) {
+                digits.pop();
+            }
+        }
+
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((first, rest)) => {
+                write!(f, "{}", first)?;
+                for chunk in rest.iter().rev() {
+                    write!(f, "{:09}", chunk)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Decides how worry levels are stored and how they're reduced after each inspection.
+pub trait WorryRelief {
+    type Worry: Clone;
+
+    fn worry(&self, item: Item) -> Self::Worry;
+
+    // New worry level after the monkey inspects the item and relief is applied.
+    fn inspect(&self, worry: &Self::Worry, operation: &(Operator, Operand)) -> Self::Worry;
+
+    fn is_divisible(&self, worry: &Self::Worry, divisible_by: Item) -> bool;
+}
+
+// Part 1: worry levels are divided by 3 after each inspection.
+#[derive(Debug)]
+pub struct DivideByThree;
+
+impl WorryRelief for DivideByThree {
+    type Worry = Item;
+
+    fn worry(&self, item: Item) -> Item {
+        item
+    }
+
+    fn inspect(&self, worry: &Item, (operator, operand): &(Operator, Operand)) -> Item {
+        operator.apply(worry, operand) / 3
+    }
+
+    fn is_divisible(&self, worry: &Item, divisible_by: Item) -> bool {
+        worry.is_multiple_of(divisible_by)
+    }
+}
+
+// Part 2: worry levels are kept modulo the least common multiple of the monkeys' divisors, which
+// doesn't change the result of any of their tests.
+#[derive(Debug)]
+pub struct ModuloLcm {
+    pub lcm: Item,
+}
+
+impl ModuloLcm {
+    pub fn new(monkeys: &[Monkey]) -> Self {
+        fn gcd(a: Item, b: Item) -> Item {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+
+        let lcm = monkeys
+            .iter()
+            .map(|m| m.divisible_by)
+            .fold(1, |lcm, d| lcm / gcd(lcm, d) * d);
+        Self { lcm }
+    }
+}
+
+impl WorryRelief for ModuloLcm {
+    type Worry = Item;
+
+    fn worry(&self, item: Item) -> Item {
+        item % self.lcm
+    }
+
+    fn inspect(&self, worry: &Item, (operator, operand): &(Operator, Operand)) -> Item {
+        operator.apply(worry, operand) % self.lcm
+    }
+
+    fn is_divisible(&self, worry: &Item, divisible_by: Item) -> bool {
+        worry.is_multiple_of(divisible_by)
+    }
+}
+
+// Worry levels are never reduced. They grow very quickly, so this is only usable for a small
+// number of rounds.
+#[derive(Debug)]
+pub struct NoRelief;
+
+impl WorryRelief for NoRelief {
+    type Worry = BigWorry;
+
+    fn worry(&self, item: Item) -> BigWorry {
+        BigWorry::from_item(item)
+    }
+
+    fn inspect(&self, worry: &BigWorry, (operator, operand): &(Operator, Operand)) -> BigWorry {
+        let operand = match operand {
+            Operand::Old => worry.clone(),
+            Operand::Value(v) => BigWorry::from_item(*v),
+        };
+
+        match operator {
+            Operator::Sum => worry.add(&operand),
+            Operator::Mult => worry.mul(&operand),
+        }
+    }
+
+    fn is_divisible(&self, worry: &BigWorry, divisible_by: Item) -> bool {
+        worry.rem(divisible_by) == 0
+    }
+}
+
+// Number of items each monkey inspected, once `round` completed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoundSnapshot {
+    pub round: usize,
+    pub inspections: Vec<usize>,
+}
+
+impl RoundSnapshot {
+    // Product of the two highest inspection counts.
+    pub fn monkey_business(&self) -> usize {
+        let mut inspections = self.inspections.clone();
+        inspections.sort_unstable_by(|a, b| b.cmp(a));
+        inspections.iter().take(2).product()
+    }
+}
+
+// Identifies an item by its position in the starting items, counting across all monkeys.
+pub type ItemId = usize;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackedItem<W> {
+    pub id: ItemId,
+    pub worry: W,
+}
+
+// An item thrown by a monkey, with the worry level after the inspection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Throw<W> {
+    pub round: usize,
+    pub item: ItemId,
+    pub from: usize,
+    pub to: usize,
+    pub worry: W,
+}
+
+// The cycle an item enters. Starting on `start_round`, the monkey holding the item and its worry
+// level at the end of each round repeat every `period` rounds. `holders` are the monkeys holding
+// the item at the end of each round in one period.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemCycle {
+    pub item: ItemId,
+    pub start_round: usize,
+    pub period: usize,
+    pub holders: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub struct KeepAway<'a, R: WorryRelief> {
+    monkeys: &'a [Monkey],
+    relief: R,
+    items: Vec<Vec<TrackedItem<R::Worry>>>,
+    inspections: Vec<usize>,
+    round: usize,
+    throws: Option<Vec<Throw<R::Worry>>>,
+}
+
+impl<'a, R: WorryRelief> KeepAway<'a, R> {
+    pub fn new(monkeys: &'a [Monkey], relief: R) -> Self {
+        let mut id = 0;
+        let items = monkeys
+            .iter()
+            .map(|m| {
+                m.items
+                    .iter()
+                    .map(|item| {
+                        id += 1;
+                        TrackedItem {
+                            id: id - 1,
+                            worry: relief.worry(*item),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            monkeys,
+            relief,
+            items,
+            inspections: vec![0; monkeys.len()],
+            round: 0,
+            throws: None,
+        }
+    }
+
+    // Records every throw from now on, see `throws`.
+    pub fn with_trace(mut self) -> Self {
+        self.throws = Some(vec![]);
+        self
+    }
+
+    // Throws recorded since tracing was enabled, in the order they happened.
+    pub fn throws(&self) -> &[Throw<R::Worry>] {
+        self.throws.as_deref().unwrap_or_default()
+    }
+
+    // Items held by each monkey.
+    pub fn holdings(&self) -> &[Vec<TrackedItem<R::Worry>>] {
+        &self.items
+    }
+
+    // Worry levels of the items held by each monkey.
+    pub fn items(&self) -> Vec<Vec<R::Worry>> {
+        self.items
+            .iter()
+            .map(|items| items.iter().map(|item| item.worry.clone()).collect())
+            .collect()
+    }
+
+    // Monkey `monkey` inspects the item. Returns the monkey it is thrown to and its new worry level.
+    fn inspect_item(&self, monkey: usize, worry: &R::Worry) -> (usize, R::Worry) {
+        let monkey = &self.monkeys[monkey];
+        let worry = self.relief.inspect(worry, &monkey.operation);
+        let target = if self.relief.is_divisible(&worry, monkey.divisible_by) {
+            monkey.monkey1
+        } else {
+            monkey.monkey2
+        };
+        (target, worry)
+    }
+
+    pub fn play_round(&mut self) -> RoundSnapshot {
+        for i in 0..self.monkeys.len() {
+            let items = std::mem::take(&mut self.items[i]);
+            self.inspections[i] += items.len();
+            for item in items {
+                let (target, worry) = self.inspect_item(i, &item.worry);
+                if let Some(throws) = &mut self.throws {
+                    throws.push(Throw {
+                        round: self.round + 1,
+                        item: item.id,
+                        from: i,
+                        to: target,
+                        worry: worry.clone(),
+                    });
+                }
+                self.items[target].push(TrackedItem { id: item.id, worry });
+            }
+        }
+
+        self.round += 1;
+        self.snapshot()
+    }
+
+    // Plays `rounds` rounds, returning a snapshot for each of them.
+    pub fn play(&mut self, rounds: usize) -> Vec<RoundSnapshot> {
+        (0..rounds).map(|_| self.play_round()).collect()
+    }
+
+    pub fn snapshot(&self) -> RoundSnapshot {
+        RoundSnapshot {
+            round: self.round,
+            inspections: self.inspections.clone(),
+        }
+    }
+
+    // Finds the cycle the item enters, looking at most `max_rounds` rounds ahead.
+    //
+    // Items never affect each other, so the monkey holding an item and its worry level at the end
+    // of a round only depend on where it was at the end of the previous round. Once that pair
+    // repeats, the item is in a cycle.
+    pub fn item_cycle(&self, item: ItemId, max_rounds: usize) -> Option<ItemCycle>
+    where
+        R::Worry: Eq + Hash,
+    {
+        let (mut holder, mut worry) =
+            self.items.iter().enumerate().find_map(|(monkey, items)| {
+                items
+                    .iter()
+                    .find(|tracked| tracked.id == item)
+                    .map(|tracked| (monkey, tracked.worry.clone()))
+            })?;
+
+        let mut seen = HashMap::new();
+        let mut holders = vec![];
+        for round in self.round..=self.round + max_rounds {
+            if let Some(start_round) = seen.insert((holder, worry.clone()), round) {
+                return Some(ItemCycle {
+                    item,
+                    start_round,
+                    period: round - start_round,
+                    holders: holders[start_round - self.round..].to_vec(),
+                });
+            }
+            holders.push(holder);
+
+            // The item is inspected again in the same round when thrown to a monkey that hasn't
+            // had its turn yet.
+            loop {
+                let (target, new_worry) = self.inspect_item(holder, &worry);
+                let next_round = target <= holder;
+                holder = target;
+                worry = new_worry;
+                if next_round {
+                    break;
+                }
+            }
+        }
+        None
+    }
+}
+
+fn print_snapshot(snapshot: &RoundSnapshot) {
+    println!("Round {}", snapshot.round);
+    for (monkey, inspections) in snapshot.inspections.iter().enumerate() {
+        println!("\tMonkey: {}, Inspections: {}", monkey, inspections);
+    }
+    println!();
+}
+
+fn main() {
+    // let input = include_str!("day11_sample.txt");
+    let input = include_str!("day11.txt");
+    let monkeys = parse_monkeys(input).unwrap();
+
+    let snapshots = KeepAway::new(&monkeys, DivideByThree).play(20);
+    let last = snapshots.last().unwrap();
+    print_snapshot(last);
+    println!("Part 1: {}", last.monkey_business());
+
+    let mut keep_away = KeepAway::new(&monkeys, ModuloLcm::new(&monkeys));
+
+    // Pass "--trace" to see the cycle each item enters.
+    if std::env::args().any(|arg| arg == "--trace") {
+        let num_items = monkeys.iter().map(|m| m.items.len()).sum::<usize>();
+        for item in 0..num_items {
+            match keep_away.item_cycle(item, 10000) {
+                Some(cycle) => println!(
+                    "Item {}: cycle of {} rounds from round {}",
+                    item, cycle.period, cycle.start_round
+                ),
+                None => println!("Item {}: no cycle within 10000 rounds", item),
+            }
+        }
+    }
+
+    let snapshots = keep_away.play(10000);
+    for snapshot in snapshots.iter().filter(|s| s.round % 1000 == 0) {
+        print_snapshot(snapshot);
+    }
+    println!("Part 2: {}", snapshots.last().unwrap().monkey_business());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monkey(
+        items: &[Item],
+        operation: (Operator, Operand),
+        divisible_by: Item,
+        monkey1: usize,
+        monkey2: usize,
+    ) -> Monkey {
+        Monkey {
+            items: items.to_vec(),
+            operation,
+            divisible_by,
+            monkey1,
+            monkey2,
+        }
+    }
+
+    fn sample_monkeys() -> Vec<Monkey> {
+        vec![
+            monkey(&[79, 98], (Operator::Mult, Operand::Value(19)), 23, 2, 3),
+            monkey(
+                &[54, 65, 75, 74],
+                (Operator::Sum, Operand::Value(6)),
+                19,
+                2,
+                0,
+            ),
+            monkey(&[79, 60, 97], (Operator::Mult, Operand::Old), 13, 1, 3),
+            monkey(&[74], (Operator::Sum, Operand::Value(3)), 17, 0, 1),
+        ]
+    }
+
+    #[test]
+    fn parses_sample() {
+        let input = include_str!("day11_sample.txt");
+        assert_eq!(parse_monkeys(input).unwrap(), sample_monkeys());
+
+        let input = input.replace('\n', "\r\n");
+        assert_eq!(parse_monkeys(&input).unwrap(), sample_monkeys());
+    }
+
+    #[test]
+    fn parses_operations() {
+        let notes = "Monkey 0:
+  Starting items:
+  Operation: new = old + old
+  Test: divisible by 2
+    If true: throw to monkey 1
+    If false: throw to monkey 0";
+        let monkey = notes.parse::<Monkey>().unwrap();
+        assert!(monkey.items.is_empty());
+        assert_eq!(monkey.operation, (Operator::Sum, Operand::Old));
+
+        let notes = notes.replace("old + old", "3 * old");
+        let monkey = notes.parse::<Monkey>().unwrap();
+        assert_eq!(monkey.operation, (Operator::Mult, Operand::Value(3)));
+
+        let notes = notes.replace("3 * old", "3 * 4");
+        assert_eq!(
+            notes.parse::<Monkey>(),
+            Err(ParseInputError(
+                "Operation must use the old value: \"new = 3 * 4\"".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn reports_errors() {
+        let input = include_str!("day11_sample.txt");
+
+        let notes = input.replace("Test: divisible by 19", "Check: divisible by 19");
+        assert_eq!(
+            parse_monkeys(&notes),
+            Err(ParseInputError(
+                "Monkey 1: Expected \"Test\" section, found \"Check: divisible by 19\"".to_string()
+            ))
+        );
+
+        let notes = input.replace("79, 60, 97", "79, sixty, 97");
+        assert_eq!(
+            parse_monkeys(&notes),
+            Err(ParseInputError(
+                "Monkey 2: Invalid number \"sixty\" in Starting items".to_string()
+            ))
+        );
+
+        let notes = input.replace("If false: throw to monkey 1", "If false: throw to monkey 4");
+        assert_eq!(
+            parse_monkeys(&notes),
+            Err(ParseInputError(
+                "Monkey 3: throws to monkey 4, but there are only 4 monkeys".to_string()
+            ))
+        );
+
+        let notes = input.replace("    If false: throw to monkey 3\n\nMonkey 3", "\nMonkey 3");
+        assert_eq!(
+            parse_monkeys(&notes),
+            Err(ParseInputError(
+                "Monkey 2: Missing \"If false\" section".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn divide_by_three() {
+        let monkeys = sample_monkeys();
+        let mut keep_away = KeepAway::new(&monkeys, DivideByThree);
+        keep_away.play_round();
+        assert_eq!(
+            keep_away.items(),
+            &[
+                vec![20, 23, 27, 26],
+                vec![2080, 25, 167, 207, 401, 1046],
+                vec![],
+                vec![]
+            ]
+        );
+
+        let snapshots = keep_away.play(19);
+        let last = snapshots.last().unwrap();
+        assert_eq!(last.round, 20);
+        assert_eq!(last.inspections, vec![101, 95, 7, 105]);
+        assert_eq!(last.monkey_business(), 10605);
+    }
+
+    #[test]
+    fn traces_items() {
+        let monkeys = sample_monkeys();
+        let mut keep_away = KeepAway::new(&monkeys, DivideByThree).with_trace();
+        keep_away.play_round();
+
+        let ids = keep_away
+            .holdings()
+            .iter()
+            .map(|items| items.iter().map(|item| item.id).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![vec![2, 3, 4, 5], vec![6, 9, 0, 1, 7, 8], vec![], vec![]]
+        );
+
+        assert_eq!(keep_away.throws().len(), 14);
+        let item_0 = keep_away
+            .throws()
+            .iter()
+            .filter(|throw| throw.item == 0)
+            .map(|throw| (throw.from, throw.to, throw.worry))
+            .collect::<Vec<_>>();
+        assert_eq!(item_0, vec![(0, 3, 500), (3, 1, 167)]);
+    }
+
+    #[test]
+    fn finds_item_cycles() {
+        let monkeys = sample_monkeys();
+        let keep_away = KeepAway::new(&monkeys, ModuloLcm::new(&monkeys));
+        let num_items = monkeys.iter().map(|m| m.items.len()).sum::<usize>();
+
+        for item in 0..num_items {
+            let cycle = keep_away.item_cycle(item, 10000).unwrap();
+            assert_eq!(cycle.holders.len(), cycle.period);
+
+            // Play the game up to the start of the cycle, and once more through it.
+            let find = |keep_away: &KeepAway<ModuloLcm>| {
+                keep_away
+                    .holdings()
+                    .iter()
+                    .enumerate()
+                    .find_map(|(monkey, items)| {
+                        items
+                            .iter()
+                            .find(|tracked| tracked.id == item)
+                            .map(|tracked| (monkey, tracked.worry))
+                    })
+                    .unwrap()
+            };
+            let mut game = KeepAway::new(&monkeys, ModuloLcm::new(&monkeys));
+            game.play(cycle.start_round);
+            let start = find(&game);
+            assert_eq!(start.0, cycle.holders[0]);
+            game.play(cycle.period);
+            assert_eq!(find(&game), start);
+        }
+    }
+
+    #[test]
+    fn modulo_lcm() {
+        let monkeys = sample_monkeys();
+        let relief = ModuloLcm::new(&monkeys);
+        assert_eq!(relief.lcm, 23 * 19 * 13 * 17);
+
+        let snapshots = KeepAway::new(&monkeys, relief).play(10000);
+        assert_eq!(snapshots[0].inspections, vec![2, 4, 3, 6]);
+        assert_eq!(snapshots[19].inspections, vec![99, 97, 8, 103]);
+        assert_eq!(snapshots[9999].monkey_business(), 2713310158);
+    }
+
+    #[test]
+    fn no_relief_matches_modulo_lcm() {
+        let monkeys = sample_monkeys();
+        let no_relief = KeepAway::new(&monkeys, NoRelief).play(10);
+        let modulo_lcm = KeepAway::new(&monkeys, ModuloLcm::new(&monkeys)).play(10);
+        assert_eq!(no_relief, modulo_lcm);
+    }
+
+    #[test]
+    fn big_worry_arithmetic() {
+        let a = BigWorry::from_item(u64::MAX);
+        let b = BigWorry::from_item(12345);
+        assert_eq!(a.add(&b).to_string(), "18446744073709563960");
+        assert_eq!(
+            a.mul(&a).to_string(),
+            "340282366920938463426481119284349108225"
+        );
+        assert_eq!(a.mul(&a).rem(1_000_000_007), 114_944_269);
+        assert_eq!(BigWorry::default().to_string(), "0");
     }
-    inspections.sort();
-    let top2 = inspections.iter().rev().take(2).collect::<Vec<_>>();
-    println!("Top 2: {:?}", top2);
-    println!("Monkey Business: {}", top2[0] * top2[1]);
 }
@@ -1,13 +1,17 @@
-use std::collections::HashMap;
-use std::io::Write;
-use std::thread;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fs;
 
+#[allow(dead_code)]
 const SAMPLE_INPUT: &str = r"Sabqponm
 abcryxxl
 accszExk
 acctuvwj
 abdefghi";
 
+#[derive(Debug)]
+pub struct ParseInputError(pub String);
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Coordinate {
     pub x: usize,
@@ -18,9 +22,13 @@ impl Coordinate {
     pub fn new(x: usize, y: usize) -> Self {
         Self { x, y }
     }
+
+    pub fn manhattan_distance(&self, other: &Coordinate) -> usize {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Direction {
     Up,
     Right,
@@ -28,6 +36,67 @@ pub enum Direction {
     Left,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+}
+
+// A path between two coordinates. `coordinates` includes both ends, and `length` is the number of
+// steps taken.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Path {
+    pub coordinates: Vec<Coordinate>,
+    pub length: usize,
+}
+
+// Number of steps needed to go from each cell of a map to a target, indexed row by row. Cells that
+// can't reach the target have no distance.
+#[derive(Debug)]
+pub struct DistanceField {
+    pub width: usize,
+    pub height: usize,
+    distances: Vec<Option<usize>>,
+}
+
+impl DistanceField {
+    pub fn distance_at(&self, coordinate: &Coordinate) -> Option<usize> {
+        self.distances[coordinate.y * self.width + coordinate.x]
+    }
+
+    // Draws the field as a plain (P3) PPM image, one pixel per cell. Cells close to the target
+    // are bright, far away cells are dark blue and unreachable cells are black.
+    pub fn render_ppm(&self) -> String {
+        let max = self
+            .distances
+            .iter()
+            .flatten()
+            .max()
+            .copied()
+            .unwrap_or(0)
+            .max(1);
+        let mut content = format!("P3\n{} {}\n255\n", self.width, self.height);
+        for row in self.distances.chunks(self.width) {
+            let pixels = row
+                .iter()
+                .map(|distance| match distance {
+                    Some(distance) => {
+                        let level = 255 - distance * 255 / max;
+                        format!("{} {} {}", level, level, 64 + level * 191 / 255)
+                    }
+                    None => "0 0 0".to_string(),
+                })
+                .collect::<Vec<_>>();
+            content.push_str(&pixels.join(" "));
+            content.push('\n');
+        }
+        content
+    }
+}
+
 #[derive(Debug)]
 pub struct Map {
     pub width: usize,
@@ -67,7 +136,7 @@ impl Map {
                 if from.y >= self.height - 1 {
                     None
                 } else {
-                    Some(Coordinate::new(from.x, from.y - 1))
+                    Some(Coordinate::new(from.x, from.y + 1))
                 }
             }
 
@@ -88,155 +157,437 @@ impl Map {
             }
         }
     }
-}
 
-fn calculate_shortest_distance(
-    map: &Map,
-    current_position: Coordinate,
-    end_position: Coordinate,
-    current_distance: usize,
-    visited: &mut HashMap<Coordinate, usize>,
-) -> Option<usize> {
-    if current_position == end_position {
-        return Some(current_distance);
-    }
-
-    // This coordinate has already been visited and the distance is smaller than the current distance,
-    // so we just return it.
-    if let Some(d) = visited.get(&current_position) {
-        if *d <= current_distance {
-            return None;
-        }
-    }
-
-    visited.insert(current_position, current_distance);
-
-    let mut shortest_distance = None;
-    // North
-    if current_position.y > 0 {
-        let c = Coordinate::new(current_position.x, current_position.y - 1);
-        if map.can_move(&current_position, &c) {
-            let new_distance =
-                calculate_shortest_distance(map, c, end_position, current_distance + 1, visited);
-            if let Some(d) = new_distance {
-                if shortest_distance.is_none() || d < shortest_distance.unwrap() {
-                    shortest_distance = new_distance;
+    // Coordinates that can be reached in one step from `from`.
+    pub fn neighbors<'a>(&'a self, from: &'a Coordinate) -> impl Iterator<Item = Coordinate> + 'a {
+        Direction::ALL
+            .into_iter()
+            .filter_map(|direction| self.coordinate_at(from, direction))
+            .filter(|to| self.can_move(from, to))
+    }
+
+    // Coordinates that can reach `to` in one step.
+    pub fn reverse_neighbors<'a>(
+        &'a self,
+        to: &'a Coordinate,
+    ) -> impl Iterator<Item = Coordinate> + 'a {
+        Direction::ALL
+            .into_iter()
+            .filter_map(|direction| self.coordinate_at(to, direction))
+            .filter(|from| self.can_move(from, to))
+    }
+
+    // Runs a single breadth-first search backwards from `end`, with the climbing rule inverted,
+    // to find the distance from every cell to `end`.
+    pub fn distances_to(&self, end: Coordinate) -> DistanceField {
+        let mut distances = vec![None; self.height_map.len()];
+        let mut queue = VecDeque::from([end]);
+        distances[self.pos_at(end.y, end.x)] = Some(0);
+
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[self.pos_at(current.y, current.x)].unwrap();
+            for previous in self.reverse_neighbors(&current) {
+                let pos = self.pos_at(previous.y, previous.x);
+                if distances[pos].is_none() {
+                    distances[pos] = Some(distance + 1);
+                    queue.push_back(previous);
                 }
             }
         }
+
+        DistanceField {
+            width: self.width,
+            height: self.height,
+            distances,
+        }
+    }
+
+    // The cell at `height` closest to the target of `field`, with its distance.
+    pub fn closest_at_height(
+        &self,
+        field: &DistanceField,
+        height: u8,
+    ) -> Option<(Coordinate, usize)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Coordinate::new(x, y)))
+            .filter(|c| self.height_at_coord(c) == height)
+            .filter_map(|c| field.distance_at(&c).map(|distance| (c, distance)))
+            .min_by_key(|(_, distance)| *distance)
+    }
+
+    // Rebuilds the path to `end`, following the coordinate each position was reached from.
+    fn build_path(
+        &self,
+        came_from: &[Option<Coordinate>],
+        start: Coordinate,
+        end: Coordinate,
+    ) -> Path {
+        let mut coordinates = vec![end];
+        let mut current = end;
+        while current != start {
+            current = came_from[self.pos_at(current.y, current.x)].unwrap();
+            coordinates.push(current);
+        }
+        coordinates.reverse();
+        Path {
+            length: coordinates.len() - 1,
+            coordinates,
+        }
     }
 
-    // South
-    if current_position.y < map.height - 1 {
-        let c = Coordinate::new(current_position.x, current_position.y + 1);
-        if map.can_move(&current_position, &c) {
-            let new_distance =
-                calculate_shortest_distance(map, c, end_position, current_distance + 1, visited);
-            if let Some(d) = new_distance {
-                if shortest_distance.is_none() || d < shortest_distance.unwrap() {
-                    shortest_distance = new_distance;
+    // Breadth-first search. Every step has the same cost, so `end` is first reached through a
+    // shortest path.
+    pub fn bfs(&self, start: Coordinate, end: Coordinate) -> Option<Path> {
+        let mut came_from = vec![None; self.height_map.len()];
+        let mut visited = vec![false; self.height_map.len()];
+        let mut queue = VecDeque::from([start]);
+        visited[self.pos_at(start.y, start.x)] = true;
+
+        while let Some(current) = queue.pop_front() {
+            if current == end {
+                return Some(self.build_path(&came_from, start, end));
+            }
+
+            for next in self.neighbors(&current) {
+                let pos = self.pos_at(next.y, next.x);
+                if !visited[pos] {
+                    visited[pos] = true;
+                    came_from[pos] = Some(current);
+                    queue.push_back(next);
                 }
             }
         }
+        None
     }
 
-    // Left
-    if current_position.x > 0 {
-        let c = Coordinate::new(current_position.x - 1, current_position.y);
-        if map.can_move(&current_position, &c) {
-            let new_distance =
-                calculate_shortest_distance(map, c, end_position, current_distance + 1, visited);
-            if let Some(d) = new_distance {
-                if shortest_distance.is_none() || d < shortest_distance.unwrap() {
-                    shortest_distance = new_distance;
+    // Expands the coordinate with the lowest distance so far plus the `heuristic` estimate of the
+    // distance left. The heuristic must never overestimate the distance.
+    fn best_first(
+        &self,
+        start: Coordinate,
+        end: Coordinate,
+        heuristic: impl Fn(&Coordinate) -> usize,
+    ) -> Option<Path> {
+        let mut came_from = vec![None; self.height_map.len()];
+        let mut distances = vec![usize::MAX; self.height_map.len()];
+        let mut queue = BinaryHeap::new();
+        distances[self.pos_at(start.y, start.x)] = 0;
+        queue.push(Reverse((heuristic(&start), 0, start.y, start.x)));
+
+        while let Some(Reverse((_, distance, y, x))) = queue.pop() {
+            let current = Coordinate::new(x, y);
+            if current == end {
+                return Some(self.build_path(&came_from, start, end));
+            }
+
+            // A shorter way to this coordinate has already been expanded.
+            if distance > distances[self.pos_at(y, x)] {
+                continue;
+            }
+
+            for next in self.neighbors(&current) {
+                let pos = self.pos_at(next.y, next.x);
+                let next_distance = distance + 1;
+                if next_distance < distances[pos] {
+                    distances[pos] = next_distance;
+                    came_from[pos] = Some(current);
+                    let estimate = next_distance + heuristic(&next);
+                    queue.push(Reverse((estimate, next_distance, next.y, next.x)));
                 }
             }
         }
+        None
+    }
+
+    pub fn dijkstra(&self, start: Coordinate, end: Coordinate) -> Option<Path> {
+        self.best_first(start, end, |_| 0)
+    }
+
+    // Uses the Manhattan distance to the end as the heuristic.
+    pub fn a_star(&self, start: Coordinate, end: Coordinate) -> Option<Path> {
+        self.best_first(start, end, |c| c.manhattan_distance(&end))
+    }
+
+    // Arrow for each coordinate in the path, pointing to the next step. The last coordinate gets
+    // an 'E'.
+    fn path_arrows(&self, path: &Path) -> Vec<Option<char>> {
+        let mut arrows = vec![None; self.height_map.len()];
+        for step in path.coordinates.windows(2) {
+            let (from, to) = (step[0], step[1]);
+            let arrow = if to.x > from.x {
+                '>'
+            } else if to.x < from.x {
+                '<'
+            } else if to.y > from.y {
+                'v'
+            } else {
+                '^'
+            };
+            arrows[self.pos_at(from.y, from.x)] = Some(arrow);
+        }
+
+        // A path with no steps never leaves its start, so it has no end to mark.
+        if let [_, .., end] = path.coordinates[..] {
+            arrows[self.pos_at(end.y, end.x)] = Some('E');
+        }
+        arrows
     }
 
-    // Right
-    if current_position.x < map.width - 1 {
-        let c = Coordinate::new(current_position.x + 1, current_position.y);
-        if map.can_move(&current_position, &c) {
-            let new_distance =
-                calculate_shortest_distance(map, c, end_position, current_distance + 1, visited);
-            if let Some(d) = new_distance {
-                if shortest_distance.is_none() || d < shortest_distance.unwrap() {
-                    shortest_distance = new_distance;
+    // Draws the path with the puzzle notation: an arrow on each step pointing to the next one,
+    // 'E' at the end and '.' everywhere else.
+    pub fn render_path(&self, path: &Path) -> String {
+        let arrows = self.path_arrows(path);
+        let mut content = String::with_capacity((self.width + 1) * self.height);
+        for row in arrows.chunks(self.width) {
+            content.extend(row.iter().map(|arrow| arrow.unwrap_or('.')));
+            content.push('\n');
+        }
+        content
+    }
+
+    // Draws the map for an ANSI terminal, shading each cell by its height from dark (a) to light
+    // (z). Cells off the path show their height, and the path is drawn with arrows in bold red.
+    pub fn render_path_ansi(&self, path: &Path) -> String {
+        let arrows = self.path_arrows(path);
+        let mut content = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let height = self.height_at(y, x);
+                // The 256 colour palette has 24 shades of grey, starting at 232.
+                let shade = 232 + (height - b'a') as usize * 23 / 25;
+                content.push_str(&format!("\x1b[48;5;{}m", shade));
+                match arrows[self.pos_at(y, x)] {
+                    Some(arrow) => content.push_str(&format!("\x1b[1;31m{}\x1b[22;39m", arrow)),
+                    None => content.push(height as char),
                 }
             }
+            content.push_str("\x1b[0m\n");
         }
+        content
     }
-
-    shortest_distance
 }
 
-fn main() {
-    let child = thread::Builder::new()
-        .stack_size(32 * 1024 * 1024)
-        .spawn(|| {
-            let input = include_str!("day12.txt");
-            // let input = SAMPLE_INPUT;
-            let map_width = input.lines().next().unwrap().len();
-            let mut map_height = 0;
-            let mut heights = vec![];
-            let mut initial_position = Coordinate::default();
-            let mut destination = Coordinate::default();
-            for line in input.lines() {
-                for (position, char) in line.chars().enumerate() {
-                    match char {
-                        'a'..='z' => {
-                            heights.push(char as u8);
-                        }
-                        'S' => {
-                            initial_position = Coordinate::new(position, map_height);
-                            heights.push('a' as u8);
-                        }
-                        'E' => {
-                            destination = Coordinate::new(position, map_height);
-                            heights.push('z' as u8);
-                        }
-                        _ => {}
-                    }
+// Parses the height map, returning it with the start and end coordinates.
+pub fn parse_input(input: &str) -> Result<(Map, Coordinate, Coordinate), ParseInputError> {
+    let width = input.lines().next().unwrap_or_default().len();
+    let mut height = 0;
+    let mut heights = vec![];
+    let mut start = None;
+    let mut end = None;
+    for line in input.lines() {
+        if line.len() != width {
+            return Err(ParseInputError(format!(
+                "Line {} has length {}, expected {}",
+                height + 1,
+                line.len(),
+                width
+            )));
+        }
+
+        for (x, char) in line.chars().enumerate() {
+            let char = match char {
+                'S' => {
+                    start = Some(Coordinate::new(x, height));
+                    'a'
+                }
+                'E' => {
+                    end = Some(Coordinate::new(x, height));
+                    'z'
+                }
+                'a'..='z' => char,
+                _ => {
+                    return Err(ParseInputError(format!(
+                        "Invalid height '{}' at {}, {}",
+                        char, x, height
+                    )))
                 }
-                map_height += 1;
-            }
-            println!("{}, {}", map_width, map_height);
-            let map = Map {
-                width: map_width,
-                height: map_height,
-                height_map: heights,
             };
+            heights.push(char as u8);
+        }
+        height += 1;
+    }
 
-            let mut visited = HashMap::new();
-            let shortest_distance =
-                calculate_shortest_distance(&map, initial_position, destination, 0, &mut visited);
-            println!("Part 1: {:?}", shortest_distance);
-
-            let mut best_distance = usize::MAX;
-            for y in 0..map.height {
-                for x in 0..map.width {
-                    print!("\r{}:{}", y, x);
-                    std::io::stdout().flush().unwrap();
-                    if map.height_at(y, x) == 'a' as u8 {
-                        visited.clear();
-                        let shortest_distance = calculate_shortest_distance(
-                            &map,
-                            Coordinate::new(x, y),
-                            destination,
-                            0,
-                            &mut visited,
-                        );
-                        if shortest_distance.is_some() && shortest_distance.unwrap() < best_distance
-                        {
-                            best_distance = shortest_distance.unwrap();
-                            println!("Best: {}, {}, {}", y, x, best_distance);
-                        }
-                    }
-                }
+    let start = start.ok_or_else(|| ParseInputError("Missing start".to_string()))?;
+    let end = end.ok_or_else(|| ParseInputError("Missing end".to_string()))?;
+    let map = Map {
+        width,
+        height,
+        height_map: heights,
+    };
+    Ok((map, start, end))
+}
+
+fn main() {
+    let input = include_str!("day12.txt");
+    // let input = SAMPLE_INPUT;
+    let (map, start, end) = parse_input(input).unwrap();
+    println!("{}, {}", map.width, map.height);
+
+    let field = map.distances_to(end);
+    println!("Part 1: {:?}", field.distance_at(&start));
+    println!(
+        "Part 2: {:?}",
+        map.closest_at_height(&field, b'a')
+            .map(|(_, distance)| distance)
+    );
+
+    // Pass "--route" to draw the shortest route, "--color" to draw it with ANSI colours and
+    // "--ppm <path>" to write the distance field as an image.
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(route) = map.bfs(start, end) {
+        if args.iter().any(|arg| arg == "--route") {
+            println!("{}", map.render_path(&route));
+        }
+        if args.iter().any(|arg| arg == "--color") {
+            println!("{}", map.render_path_ansi(&route));
+        }
+    }
+
+    if let Some(i) = args.iter().position(|arg| arg == "--ppm") {
+        let path = args.get(i + 1).expect("--ppm requires an output path");
+        fs::write(path, field.render_ppm()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_valid_path(map: &Map, path: &Path, start: Coordinate, end: Coordinate) {
+        assert_eq!(path.coordinates.first(), Some(&start));
+        assert_eq!(path.coordinates.last(), Some(&end));
+        assert_eq!(path.coordinates.len(), path.length + 1);
+        for step in path.coordinates.windows(2) {
+            assert_eq!(step[0].manhattan_distance(&step[1]), 1);
+            assert!(map.can_move(&step[0], &step[1]));
+        }
+    }
+
+    #[test]
+    fn coordinate_at() {
+        let (map, _, _) = parse_input(SAMPLE_INPUT).unwrap();
+        let from = Coordinate::new(3, 2);
+        assert_eq!(
+            map.coordinate_at(&from, Direction::Up),
+            Some(Coordinate::new(3, 1))
+        );
+        assert_eq!(
+            map.coordinate_at(&from, Direction::Down),
+            Some(Coordinate::new(3, 3))
+        );
+        assert_eq!(
+            map.coordinate_at(&from, Direction::Left),
+            Some(Coordinate::new(2, 2))
+        );
+        assert_eq!(
+            map.coordinate_at(&from, Direction::Right),
+            Some(Coordinate::new(4, 2))
+        );
+        assert_eq!(
+            map.coordinate_at(&Coordinate::new(7, 4), Direction::Down),
+            None
+        );
+    }
+
+    #[test]
+    fn shortest_paths() {
+        let (map, start, end) = parse_input(SAMPLE_INPUT).unwrap();
+        for path in [
+            map.bfs(start, end),
+            map.dijkstra(start, end),
+            map.a_star(start, end),
+        ] {
+            let path = path.unwrap();
+            assert_eq!(path.length, 31);
+            assert_valid_path(&map, &path, start, end);
+        }
+    }
+
+    #[test]
+    fn distance_field() {
+        let (map, start, end) = parse_input(SAMPLE_INPUT).unwrap();
+        let field = map.distances_to(end);
+        assert_eq!(field.distance_at(&start), Some(31));
+        assert_eq!(field.distance_at(&end), Some(0));
+
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let c = Coordinate::new(x, y);
+                assert_eq!(
+                    field.distance_at(&c),
+                    map.bfs(c, end).map(|path| path.length)
+                );
             }
-            println!("Part 2: {:?}", best_distance);
-        })
-        .unwrap();
-    child.join().unwrap();
+        }
+
+        assert_eq!(
+            map.closest_at_height(&field, b'a'),
+            Some((Coordinate::new(0, 4), 29))
+        );
+    }
+
+    #[test]
+    fn renders_distance_field() {
+        let (map, _, end) = parse_input("SaE").unwrap();
+        let ppm = map.distances_to(end).render_ppm();
+        assert_eq!(ppm, "P3\n3 1\n255\n0 0 0 0 0 0 255 255 255\n");
+    }
+
+    #[test]
+    fn renders_path() {
+        let (map, start, end) = parse_input("Sbcdefghijklm\nEyxwvutsrqpon").unwrap();
+        let path = map.bfs(start, end).unwrap();
+        assert_eq!(path.length, 25);
+        assert_eq!(map.render_path(&path), ">>>>>>>>>>>>v\nE<<<<<<<<<<<<\n");
+    }
+
+    #[test]
+    fn renders_sample_path() {
+        let (map, start, end) = parse_input(SAMPLE_INPUT).unwrap();
+        let path = map.bfs(start, end).unwrap();
+        let rendered = map.render_path(&path);
+        assert_eq!(rendered.lines().count(), map.height);
+        assert_eq!(rendered.chars().filter(|c| "<>^v".contains(*c)).count(), 31);
+        assert_eq!(rendered.lines().nth(2).unwrap().chars().nth(5), Some('E'));
+    }
+
+    #[test]
+    fn renders_path_ansi() {
+        let (map, start, end) = parse_input("Sbcdefghijklm\nEyxwvutsrqpon").unwrap();
+        let path = map.bfs(start, end).unwrap();
+        let rendered = map.render_path_ansi(&path);
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.ends_with("\x1b[0m")));
+
+        // The start, at height 'a', is the darkest grey, and the end, at height 'z', the lightest.
+        assert!(lines[0].starts_with("\x1b[48;5;232m\x1b[1;31m>\x1b[22;39m"));
+        assert!(lines[1].starts_with("\x1b[48;5;255m\x1b[1;31mE\x1b[22;39m"));
+        for (arrow, count) in [('>', 12), ('v', 1), ('<', 12), ('E', 1)] {
+            let highlighted = format!("\x1b[1;31m{}\x1b[22;39m", arrow);
+            assert_eq!(rendered.matches(&highlighted).count(), count, "{}", arrow);
+        }
+    }
+
+    #[test]
+    fn renders_path_without_steps() {
+        let (map, start, _) = parse_input("SbE").unwrap();
+        let path = Path {
+            coordinates: vec![start],
+            length: 0,
+        };
+        assert_eq!(map.render_path(&path), "...\n");
+        assert_eq!(
+            map.render_path_ansi(&path),
+            "\x1b[48;5;232ma\x1b[48;5;232mb\x1b[48;5;255mz\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn unreachable_end() {
+        let (map, start, end) = parse_input("SaE").unwrap();
+        assert_eq!(map.bfs(start, end), None);
+        assert_eq!(map.dijkstra(start, end), None);
+        assert_eq!(map.a_star(start, end), None);
+    }
 }
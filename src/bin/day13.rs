@@ -1,8 +1,11 @@
 use std::{
     cmp::Ordering,
-    str::{Chars, FromStr},
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
 };
 
+#[allow(dead_code)]
 const SAMPLE_INPUT: &str = r"[1,1,3,1,1]
 [1,1,5,1,1]
 
@@ -27,8 +30,41 @@ const SAMPLE_INPUT: &str = r"[1,1,3,1,1]
 [1,[2,[3,[4,[5,6,7]]]],8,9]
 [1,[2,[3,[4,[5,6,0]]]],8,9]";
 
-#[derive(Debug)]
-pub struct ParseInputError;
+// Errors found while parsing a packet. Positions are byte offsets into the parsed text.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseInputError {
+    UnexpectedChar { position: usize, found: char },
+    UnexpectedEnd { position: usize },
+    LeadingZero { position: usize },
+    NumberTooLarge { position: usize },
+    TrailingInput { position: usize },
+}
+
+impl Display for ParseInputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseInputError::UnexpectedChar { position, found } => {
+                write!(f, "unexpected '{}' at position {}", found, position)
+            }
+            ParseInputError::UnexpectedEnd { position } => {
+                write!(f, "unexpected end of input at position {}", position)
+            }
+            ParseInputError::LeadingZero { position } => {
+                write!(f, "number with a leading zero at position {}", position)
+            }
+            ParseInputError::NumberTooLarge { position } => {
+                write!(f, "number too large at position {}", position)
+            }
+            ParseInputError::TrailingInput { position } => {
+                write!(
+                    f,
+                    "unexpected input after the packet at position {}",
+                    position
+                )
+            }
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Item {
@@ -40,17 +76,32 @@ impl FromStr for Item {
     type Err = ParseInputError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(parse_item(&mut s.chars()))
+        parse_item(s)
+    }
+}
+
+// Writes the item back in the packet format, so parsing and printing a packet gives the same text.
+impl Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Item::Number(n) => write!(f, "{}", n),
+            Item::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
     }
 }
 
 impl Item {
     pub fn is_number(&self) -> bool {
-        if let Item::Number(_) = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, Item::Number(_))
     }
 
     pub fn as_number(&self) -> u32 {
@@ -61,11 +112,7 @@ impl Item {
     }
 
     pub fn is_list(&self) -> bool {
-        if let Item::List(_) = self {
-            true
-        } else {
-            false
-        }
+        matches!(self, Item::List(_))
     }
 }
 
@@ -75,49 +122,104 @@ pub struct Pair {
     pub right: Item,
 }
 
-pub fn parse_item(chars: &mut Chars) -> Item {
-    let mut result = vec![];
+// Parses a whole packet, failing if anything is left after it.
+pub fn parse_item(input: &str) -> Result<Item, ParseInputError> {
+    let mut parser = Parser { input, position: 0 };
+    let item = parser.item()?;
+    match parser.peek() {
+        None => Ok(item),
+        Some(_) => Err(ParseInputError::TrailingInput {
+            position: parser.position,
+        }),
+    }
+}
+
+// Recursive descent parser for the packet grammar:
+//   item   = number | list
+//   list   = "[" [item ("," item)*] "]"
+//   number = "0" | [1-9][0-9]*
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
 
-    let mut number: Option<u32> = None;
-    while let Some(char) = chars.next() {
-        match char {
-            '[' => {
-                let new_item = parse_item(chars);
-                result.push(new_item);
-            }
-            ']' => {
-                break;
-            }
-            '0'..='9' => {
-                let current = number.unwrap_or(0);
-                let next = char.to_digit(10).unwrap();
-                number = Some(current * 10 + next);
-            }
-            ',' => {
-                if let Some(n) = number {
-                    result.push(Item::Number(n));
-                    number = None;
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn unexpected(&self) -> ParseInputError {
+        match self.peek() {
+            Some(found) => ParseInputError::UnexpectedChar {
+                position: self.position,
+                found,
+            },
+            None => ParseInputError::UnexpectedEnd {
+                position: self.position,
+            },
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseInputError> {
+        if self.peek() != Some(expected) {
+            return Err(self.unexpected());
+        }
+        self.position += expected.len_utf8();
+        Ok(())
+    }
+
+    fn item(&mut self) -> Result<Item, ParseInputError> {
+        match self.peek() {
+            Some('[') => self.list(),
+            Some('0'..='9') => self.number(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn list(&mut self) -> Result<Item, ParseInputError> {
+        self.expect('[')?;
+        let mut items = vec![];
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Item::List(items));
+        }
+
+        loop {
+            items.push(self.item()?);
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(Item::List(items));
                 }
+                _ => return Err(self.unexpected()),
             }
-            _ => {}
         }
     }
-    if let Some(n) = number {
-        result.push(Item::Number(n))
+
+    fn number(&mut self) -> Result<Item, ParseInputError> {
+        let start = self.position;
+        let digits = self.input[start..]
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .count();
+        let text = &self.input[start..start + digits];
+        if digits > 1 && text.starts_with('0') {
+            return Err(ParseInputError::LeadingZero { position: start });
+        }
+
+        let n = text
+            .parse::<u32>()
+            .map_err(|_| ParseInputError::NumberTooLarge { position: start })?;
+        self.position += digits;
+        Ok(Item::Number(n))
     }
-    Item::List(result)
 }
 
 impl Ord for Item {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
-    }
-}
-
-impl PartialOrd for Item {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         if self.is_number() && other.is_number() {
-            return self.as_number().partial_cmp(&other.as_number());
+            return self.as_number().cmp(&other.as_number());
         }
 
         let mut left_holder: Vec<Item> = vec![];
@@ -139,15 +241,263 @@ impl PartialOrd for Item {
         };
 
         for (left, right) in left.iter().zip(right.iter()) {
-            if let Some(ordering) = left.partial_cmp(right) {
-                match ordering {
-                    Ordering::Greater | Ordering::Less => return Some(ordering),
-                    _ => {}
+            match left.cmp(right) {
+                Ordering::Greater => return Ordering::Greater,
+                Ordering::Less => return Ordering::Less,
+                _ => {}
+            }
+        }
+
+        left.len().cmp(&right.len())
+    }
+}
+
+impl PartialOrd for Item {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// A packet compared directly on its text, without building an `Item` tree. Packets must be well
+// formed; use `parse_item` to validate them first.
+#[derive(Clone, Copy, Debug)]
+pub struct RawPacket<'a>(pub &'a [u8]);
+
+impl<'a> RawPacket<'a> {
+    pub fn new(packet: &'a str) -> Self {
+        Self(packet.as_bytes())
+    }
+}
+
+impl Display for RawPacket<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(self.0))
+    }
+}
+
+// Packets are equal when they compare as equal, so `[[1]]` equals `[1]` and `1`.
+impl PartialEq for RawPacket<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RawPacket<'_> {}
+
+impl Ord for RawPacket<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_packets(self.0, other.0)
+    }
+}
+
+impl PartialOrd for RawPacket<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Number(u32),
+}
+
+// Reads the tokens of a packet, skipping commas. Comparing a number with a list is the same as
+// comparing a list holding only that number, so a number can be wrapped in place: it is returned
+// again as the next token, followed by an extra closing bracket. Wrapping only needs a counter,
+// so reading a packet never allocates.
+struct Tokens<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    wrapped: Option<u32>,
+    extra_closes: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            wrapped: None,
+            extra_closes: 0,
+        }
+    }
+
+    fn wrap(&mut self, n: u32) {
+        self.wrapped = Some(n);
+        self.extra_closes += 1;
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(n) = self.wrapped.take() {
+            return Some(Token::Number(n));
+        }
+        if self.extra_closes > 0 {
+            self.extra_closes -= 1;
+            return Some(Token::Close);
+        }
+
+        while self.bytes.get(self.position) == Some(&b',') {
+            self.position += 1;
+        }
+        let byte = *self.bytes.get(self.position)?;
+        self.position += 1;
+        match byte {
+            b'[' => Some(Token::Open),
+            b']' => Some(Token::Close),
+            _ => {
+                let mut n = (byte - b'0') as u32;
+                while let Some(digit @ b'0'..=b'9') = self.bytes.get(self.position) {
+                    n = n * 10 + (digit - b'0') as u32;
+                    self.position += 1;
                 }
+                Some(Token::Number(n))
+            }
+        }
+    }
+}
+
+// Compares two packets token by token, with the same result as comparing their `Item`s.
+pub fn compare_packets(left: &[u8], right: &[u8]) -> Ordering {
+    let mut left = Tokens::new(left);
+    let mut right = Tokens::new(right);
+    loop {
+        match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(Token::Open), Some(Token::Open)) | (Some(Token::Close), Some(Token::Close)) => {}
+            (Some(Token::Number(l)), Some(Token::Number(r))) => {
+                if l != r {
+                    return l.cmp(&r);
+                }
+            }
+            // A list ran out of items first.
+            (Some(Token::Close), _) | (None, _) => return Ordering::Less,
+            (_, Some(Token::Close)) | (_, None) => return Ordering::Greater,
+            (Some(Token::Number(l)), Some(Token::Open)) => left.wrap(l),
+            (Some(Token::Open), Some(Token::Number(r))) => right.wrap(r),
+        }
+    }
+}
+
+// Sorts the packets together with the two dividers, and returns the 1-based positions of the
+// dividers.
+pub fn divider_indices(packets: &[RawPacket], dividers: [RawPacket; 2]) -> (usize, usize) {
+    let mut all_packets = Vec::with_capacity(packets.len() + 2);
+    all_packets.extend_from_slice(packets);
+    all_packets.extend_from_slice(&dividers);
+    all_packets.sort();
+
+    let position = |divider: RawPacket| {
+        all_packets
+            .iter()
+            .position(|packet| std::ptr::eq(packet.0, divider.0))
+            .unwrap()
+            + 1
+    };
+    (position(dividers[0]), position(dividers[1]))
+}
+
+// One line of a comparison trace, nested `depth` levels deep.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExplanationStep {
+    pub depth: usize,
+    pub text: String,
+}
+
+// Step by step trace of how two packets are compared, in the format the puzzle uses to explain
+// the examples.
+#[derive(Debug)]
+pub struct Explanation {
+    pub ordering: Ordering,
+    pub steps: Vec<ExplanationStep>,
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}- {}", "  ".repeat(step.depth), step.text)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn explain_cmp(left: &Item, right: &Item) -> Explanation {
+    let mut steps = vec![];
+    let ordering = explain_items(left, right, 0, &mut steps);
+    Explanation { ordering, steps }
+}
+
+fn explain_items(
+    left: &Item,
+    right: &Item,
+    depth: usize,
+    steps: &mut Vec<ExplanationStep>,
+) -> Ordering {
+    let mut step = |depth: usize, text: String| steps.push(ExplanationStep { depth, text });
+    step(depth, format!("Compare {} vs {}", left, right));
+
+    match (left, right) {
+        (Item::Number(l), Item::Number(r)) => {
+            let ordering = l.cmp(r);
+            match ordering {
+                Ordering::Less => step(
+                    depth + 1,
+                    "Left side is smaller, so inputs are in the right order".to_string(),
+                ),
+                Ordering::Greater => step(
+                    depth + 1,
+                    "Right side is smaller, so inputs are not in the right order".to_string(),
+                ),
+                Ordering::Equal => {}
             }
+            ordering
+        }
+        (Item::Number(_), Item::List(_)) => {
+            let left = Item::List(vec![left.clone()]);
+            step(
+                depth + 1,
+                format!("Mixed types; convert left to {} and retry comparison", left),
+            );
+            explain_items(&left, right, depth + 1, steps)
         }
+        (Item::List(_), Item::Number(_)) => {
+            let right = Item::List(vec![right.clone()]);
+            step(
+                depth + 1,
+                format!(
+                    "Mixed types; convert right to {} and retry comparison",
+                    right
+                ),
+            );
+            explain_items(left, &right, depth + 1, steps)
+        }
+        (Item::List(l), Item::List(r)) => {
+            for (left, right) in l.iter().zip(r.iter()) {
+                let ordering = explain_items(left, right, depth + 1, steps);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
 
-        left.len().partial_cmp(&right.len())
+            let ordering = l.len().cmp(&r.len());
+            let text = match ordering {
+                Ordering::Less => "Left side ran out of items, so inputs are in the right order",
+                Ordering::Greater => {
+                    "Right side ran out of items, so inputs are not in the right order"
+                }
+                Ordering::Equal => return ordering,
+            };
+            steps.push(ExplanationStep {
+                depth: depth + 1,
+                text: text.to_string(),
+            });
+            ordering
+        }
     }
 }
 
@@ -158,9 +508,13 @@ fn main() {
 
     let mut lines = input.lines();
     while let Some(mut line) = lines.next() {
-        let left = line.parse::<Item>().unwrap();
+        let left = line
+            .parse::<Item>()
+            .unwrap_or_else(|e| panic!("Invalid packet {:?}: {}", line, e));
         line = lines.next().unwrap();
-        let right = line.parse::<Item>().unwrap();
+        let right = line
+            .parse::<Item>()
+            .unwrap_or_else(|e| panic!("Invalid packet {:?}: {}", line, e));
         pairs.push((left, right));
 
         lines.next(); // We don't really care about this result. It's either an empty line of EOF.
@@ -168,44 +522,63 @@ fn main() {
 
     let mut sum = 0;
     for (i, (left, right)) in pairs.iter().enumerate() {
-        if let Some(Ordering::Less) = left.partial_cmp(&right) {
+        if let Some(Ordering::Less) = left.partial_cmp(right) {
             sum += i + 1;
         }
     }
 
     println!("Part 1: {}", sum);
 
-    let divider_1 = "[[2]]".parse::<Item>().unwrap();
-    let divider_2 = "[[6]]".parse::<Item>().unwrap();
-
-    let mut all_packets = vec![divider_1.clone(), divider_2.clone()];
-
-    for (left, right) in pairs {
-        all_packets.push(left);
-        all_packets.push(right)
-    }
-
-    all_packets.sort();
-
-    let mut divider_1_index = 0;
-    let mut divider_2_index = 0;
-
-    for (i, item) in all_packets.iter().enumerate() {
-        if *item == divider_1 {
-            divider_1_index = i + 1;
-        }
-
-        if *item == divider_2 {
-            divider_2_index = i + 1;
+    // Pass "--explain" to print how every pair was compared.
+    if std::env::args().any(|arg| arg == "--explain") {
+        for (i, (left, right)) in pairs.iter().enumerate() {
+            println!("== Pair {} ==", i + 1);
+            println!("{}", explain_cmp(left, right));
         }
     }
 
+    let packets = input
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(RawPacket::new)
+        .collect::<Vec<_>>();
+    let dividers = [RawPacket::new("[[2]]"), RawPacket::new("[[6]]")];
+    let (divider_1_index, divider_2_index) = divider_indices(&packets, dividers);
+
     println!(
         "Part2 => Divider 1 = {}, Divider 2 = {}, Result = {}",
         divider_1_index,
         divider_2_index,
         divider_1_index * divider_2_index
     );
+
+    // Pass "--bench" to time sorting the packets as parsed `Item`s against sorting them as
+    // `RawPacket`s.
+    if std::env::args().any(|arg| arg == "--bench") {
+        const ITERATIONS: u32 = 200;
+        let items = pairs
+            .into_iter()
+            .flat_map(|(left, right)| [left, right])
+            .collect::<Vec<_>>();
+
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..ITERATIONS {
+            let mut sorted = items.clone();
+            let start = Instant::now();
+            sorted.sort();
+            elapsed += start.elapsed();
+        }
+        println!("Item sort:      {:?} per sort", elapsed / ITERATIONS);
+
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..ITERATIONS {
+            let mut sorted = packets.clone();
+            let start = Instant::now();
+            sorted.sort();
+            elapsed += start.elapsed();
+        }
+        println!("RawPacket sort: {:?} per sort", elapsed / ITERATIONS);
+    }
 }
 
 #[cfg(test)]
@@ -216,6 +589,99 @@ mod tests {
     fn parses_item() {
         let item = "[1,1,3,1,1]".parse::<Item>().unwrap();
         assert!(item.is_list());
+        assert_eq!(
+            item,
+            Item::List(vec![
+                Item::Number(1),
+                Item::Number(1),
+                Item::Number(3),
+                Item::Number(1),
+                Item::Number(1),
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_multi_digit_numbers() {
+        let item = "[10,[0,4294967295],123]".parse::<Item>().unwrap();
+        assert_eq!(
+            item,
+            Item::List(vec![
+                Item::Number(10),
+                Item::List(vec![Item::Number(0), Item::Number(4294967295)]),
+                Item::Number(123),
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_deep_nesting() {
+        let depth = 200;
+        let input = format!("{}7{}", "[".repeat(depth), "]".repeat(depth));
+        let mut item = input.parse::<Item>().unwrap();
+        for _ in 0..depth {
+            let Item::List(mut items) = item else {
+                panic!("expected a list");
+            };
+            assert_eq!(items.len(), 1);
+            item = items.pop().unwrap();
+        }
+        assert_eq!(item, Item::Number(7));
+    }
+
+    #[test]
+    fn round_trips_packets() {
+        for line in SAMPLE_INPUT.lines().filter(|line| !line.is_empty()) {
+            assert_eq!(line.parse::<Item>().unwrap().to_string(), line);
+        }
+        for line in include_str!("day13.txt").lines().filter(|l| !l.is_empty()) {
+            assert_eq!(line.parse::<Item>().unwrap().to_string(), line);
+        }
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert_eq!(
+            "[1,2".parse::<Item>(),
+            Err(ParseInputError::UnexpectedEnd { position: 4 })
+        );
+        assert_eq!(
+            "[1,2]]".parse::<Item>(),
+            Err(ParseInputError::TrailingInput { position: 5 })
+        );
+        assert_eq!(
+            "[1,,2]".parse::<Item>(),
+            Err(ParseInputError::UnexpectedChar {
+                position: 3,
+                found: ','
+            })
+        );
+        assert_eq!(
+            "[1,a]".parse::<Item>(),
+            Err(ParseInputError::UnexpectedChar {
+                position: 3,
+                found: 'a'
+            })
+        );
+        assert_eq!(
+            "[1 2]".parse::<Item>(),
+            Err(ParseInputError::UnexpectedChar {
+                position: 2,
+                found: ' '
+            })
+        );
+        assert_eq!(
+            "[01]".parse::<Item>(),
+            Err(ParseInputError::LeadingZero { position: 1 })
+        );
+        assert_eq!(
+            "[4294967296]".parse::<Item>(),
+            Err(ParseInputError::NumberTooLarge { position: 1 })
+        );
+        assert_eq!(
+            "".parse::<Item>(),
+            Err(ParseInputError::UnexpectedEnd { position: 0 })
+        );
     }
 
     #[test]
@@ -252,4 +718,112 @@ mod tests {
         let right = "[1,[2,[3,[4,[5,6,0]]]],8,9]".parse::<Item>().unwrap();
         assert_eq!(left.partial_cmp(&right), Some(Ordering::Greater));
     }
+
+    #[test]
+    fn explains_comparisons() {
+        let pairs = SAMPLE_INPUT
+            .split("\n\n")
+            .map(|pair| {
+                let (left, right) = pair.split_once('\n').unwrap();
+                (
+                    left.parse::<Item>().unwrap(),
+                    right.parse::<Item>().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let explanation = explain_cmp(&pairs[1].0, &pairs[1].1);
+        assert_eq!(explanation.ordering, Ordering::Less);
+        assert_eq!(
+            explanation.to_string(),
+            r"- Compare [[1],[2,3,4]] vs [[1],4]
+  - Compare [1] vs [1]
+    - Compare 1 vs 1
+  - Compare [2,3,4] vs 4
+    - Mixed types; convert right to [4] and retry comparison
+    - Compare [2,3,4] vs [4]
+      - Compare 2 vs 4
+        - Left side is smaller, so inputs are in the right order
+"
+        );
+
+        let explanation = explain_cmp(&pairs[2].0, &pairs[2].1);
+        assert_eq!(explanation.ordering, Ordering::Greater);
+        assert_eq!(
+            explanation.to_string(),
+            r"- Compare [9] vs [[8,7,6]]
+  - Compare 9 vs [8,7,6]
+    - Mixed types; convert left to [9] and retry comparison
+    - Compare [9] vs [8,7,6]
+      - Compare 9 vs 8
+        - Right side is smaller, so inputs are not in the right order
+"
+        );
+
+        let explanation = explain_cmp(&pairs[6].0, &pairs[6].1);
+        assert_eq!(explanation.ordering, Ordering::Greater);
+        assert_eq!(
+            explanation.to_string(),
+            r"- Compare [[[]]] vs [[]]
+  - Compare [[]] vs []
+    - Right side ran out of items, so inputs are not in the right order
+"
+        );
+
+        for (left, right) in &pairs {
+            assert_eq!(explain_cmp(left, right).ordering, left.cmp(right));
+        }
+    }
+
+    #[test]
+    fn compares_raw_packets() {
+        let packets = SAMPLE_INPUT
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        for left in &packets {
+            for right in &packets {
+                let expected = left.parse::<Item>().unwrap().cmp(&right.parse().unwrap());
+                assert_eq!(
+                    compare_packets(left.as_bytes(), right.as_bytes()),
+                    expected,
+                    "{} vs {}",
+                    left,
+                    right
+                );
+            }
+        }
+
+        let real = include_str!("day13.txt")
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        for pair in real.windows(2) {
+            let expected = pair[0]
+                .parse::<Item>()
+                .unwrap()
+                .cmp(&pair[1].parse().unwrap());
+            assert_eq!(
+                compare_packets(pair[0].as_bytes(), pair[1].as_bytes()),
+                expected
+            );
+        }
+
+        assert_eq!(compare_packets(b"[10]", b"[9]"), Ordering::Greater);
+        assert_eq!(compare_packets(b"[[1]]", b"[1]"), Ordering::Equal);
+        assert_eq!(compare_packets(b"[[[2]],3]", b"[2,4]"), Ordering::Less);
+        assert_eq!(compare_packets(b"[2,3]", b"[[[2]],3]"), Ordering::Equal);
+        assert_eq!(compare_packets(b"[[2,1]]", b"[2]"), Ordering::Greater);
+    }
+
+    #[test]
+    fn finds_divider_indices() {
+        let packets = SAMPLE_INPUT
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(RawPacket::new)
+            .collect::<Vec<_>>();
+        let dividers = [RawPacket::new("[[2]]"), RawPacket::new("[[6]]")];
+        assert_eq!(divider_indices(&packets, dividers), (10, 14));
+    }
 }
@@ -1,11 +1,27 @@
-use std::{collections::HashSet, str::FromStr};
+use std::{
+    collections::HashSet,
+    fs,
+    ops::Range,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
+#[allow(dead_code)]
 const SAMPLE_INPUT: &str = r"498,4 -> 498,6 -> 496,6
 503,4 -> 502,4 -> 502,9 -> 494,9";
 
+const SOURCE: Point = Point { x: 500, y: 0 };
+
 #[derive(Debug)]
 pub struct InputParseError;
 
+// The floor must be below every wall, or the walls would go through it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidFloorError {
+    pub floor: u32,
+    pub max_y: u32,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Point {
     pub x: u32,
@@ -32,102 +48,582 @@ impl FromStr for Point {
     }
 }
 
-fn main() {
-    // let input = include_str!("day14.txt");
-    let input = SAMPLE_INPUT;
-    let mut walls = vec![];
-    let (mut min_x, mut max_x) = (u32::MAX, u32::MIN);
-    let (mut min_y, mut max_y) = (0, u32::MIN);
+// Each wall is the list of points a line of the scan goes through.
+pub fn parse_walls(input: &str) -> Result<Vec<Vec<Point>>, InputParseError> {
+    input
+        .lines()
+        .map(|line| {
+            line.split("->")
+                .map(|split| split.trim().parse::<Point>())
+                .collect()
+        })
+        .collect()
+}
+
+// Smallest rectangle holding every wall and the sand source.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Bounds {
+    pub min_x: u32,
+    pub max_x: u32,
+    pub min_y: u32,
+    pub max_y: u32,
+}
 
-    for line in input.lines() {
-        let mut wall = vec![];
-        for split in line.split("->") {
-            let point = split.trim().parse::<Point>().unwrap();
-            min_x = u32::min(min_x, point.x);
-            max_x = u32::max(max_x, point.x);
-            min_y = u32::min(min_y, point.y);
-            max_y = u32::max(max_y, point.y);
-            wall.push(point);
+impl Bounds {
+    pub fn from_walls(walls: &[Vec<Point>]) -> Self {
+        let mut bounds = Bounds {
+            min_x: SOURCE.x,
+            max_x: SOURCE.x,
+            min_y: SOURCE.y,
+            max_y: SOURCE.y,
+        };
+        for point in walls.iter().flatten() {
+            bounds.min_x = u32::min(bounds.min_x, point.x);
+            bounds.max_x = u32::max(bounds.max_x, point.x);
+            bounds.min_y = u32::min(bounds.min_y, point.y);
+            bounds.max_y = u32::max(bounds.max_y, point.y);
         }
-        walls.push(wall);
+        bounds
     }
+}
 
-    let mut blocks = HashSet::new();
-    for wall in walls {
-        let mut it = wall.iter();
-        let mut current = it.next().unwrap();
-        while let Some(next) = it.next() {
-            // This bit assumes that when movin between points, it moves either in the X or Y axis,
-            // but never both. We'd need to prioritise which axis to move if that was the case.
-            let (min_x, max_x) = (u32::min(current.x, next.x), u32::max(current.x, next.x));
-            for x in min_x..=max_x {
-                blocks.insert(Point { x, y: current.y });
+// What happens below the lowest wall: sand either falls forever, or lands on an infinite floor at
+// row `y`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Abyss,
+    Floor { y: u32 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Cell {
+    Air,
+    Rock,
+    Sand,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cave {
+    pub mode: Mode,
+    pub bounds: Bounds,
+    // Column of the first cell in each row.
+    left: i64,
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Cave {
+    pub fn new(walls: &[Vec<Point>], mode: Mode) -> Result<Self, InvalidFloorError> {
+        let bounds = Bounds::from_walls(walls);
+        if let Mode::Floor { y } = mode {
+            if y <= bounds.max_y {
+                return Err(InvalidFloorError {
+                    floor: y,
+                    max_y: bounds.max_y,
+                });
             }
+        }
 
-            let (min_y, max_y) = (u32::min(current.y, next.y), u32::max(current.y, next.y));
-            for y in min_y..=max_y {
-                blocks.insert(Point { x: current.x, y });
+        let (left, right, height) = match mode {
+            // One extra column on each side, so sand can roll off the outermost walls before
+            // falling into the abyss.
+            Mode::Abyss => (
+                bounds.min_x as i64 - 1,
+                bounds.max_x as i64 + 1,
+                bounds.max_y as usize + 1,
+            ),
+            // Sand piles up in a triangle from the source to the floor, so that's as wide as it
+            // can get.
+            Mode::Floor { y } => (
+                i64::min(bounds.min_x as i64, SOURCE.x as i64 - y as i64) - 1,
+                i64::max(bounds.max_x as i64, SOURCE.x as i64 + y as i64) + 1,
+                y as usize,
+            ),
+        };
+        let width = (right - left + 1) as usize;
+
+        let mut cave = Cave {
+            mode,
+            bounds,
+            left,
+            width,
+            height,
+            cells: vec![Cell::Air; width * height],
+        };
+
+        for wall in walls {
+            let mut it = wall.iter();
+            let Some(mut current) = it.next() else {
+                continue;
+            };
+            cave.set(current.x as i64, current.y as i64, Cell::Rock);
+            for next in it {
+                // This bit assumes that when moving between points, it moves either in the X or Y
+                // axis, but never both.
+                for x in u32::min(current.x, next.x)..=u32::max(current.x, next.x) {
+                    cave.set(x as i64, current.y as i64, Cell::Rock);
+                }
+                for y in u32::min(current.y, next.y)..=u32::max(current.y, next.y) {
+                    cave.set(current.x as i64, y as i64, Cell::Rock);
+                }
+                current = next;
             }
+        }
+        Ok(cave)
+    }
 
-            current = next;
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        let col = x - self.left;
+        if col < 0 || col >= self.width as i64 || y < 0 || y >= self.height as i64 {
+            return None;
         }
+        Some(y as usize * self.width + col as usize)
     }
 
-    let mut grain_count = 0;
-    loop {
-        let mut grain = Point { x: 500, y: 0 };
+    fn set(&mut self, x: i64, y: i64, cell: Cell) {
+        let index = self.index(x, y).unwrap();
+        self.cells[index] = cell;
+    }
+
+    // The cell at a position. Anything outside the grid is air, except for the floor.
+    pub fn cell_at(&self, x: i64, y: i64) -> Cell {
+        if let Mode::Floor { y: floor } = self.mode {
+            if y == floor as i64 {
+                return Cell::Rock;
+            }
+        }
+        self.index(x, y)
+            .map(|index| self.cells[index])
+            .unwrap_or(Cell::Air)
+    }
 
-        let mut moved = true;
-        while moved {
-            moved = true;
+    pub fn is_blocked(&self, x: i64, y: i64) -> bool {
+        self.cell_at(x, y) != Cell::Air
+    }
 
-            if grain.y == max_y + 1 {
-                blocks.insert(grain);
+    // Where a grain at (x, y) moves next: down, down-left or down-right, in that order. `None`
+    // if it comes to rest.
+    pub fn next_position(&self, x: i64, y: i64) -> Option<(i64, i64)> {
+        [x, x - 1, x + 1]
+            .into_iter()
+            .map(|x| (x, y + 1))
+            .find(|&(x, y)| !self.is_blocked(x, y))
+    }
+
+    // A grain has fallen into the abyss once it leaves the grid, as there is nothing outside it to
+    // stop it.
+    fn in_abyss(&self, x: i64, y: i64) -> bool {
+        self.mode == Mode::Abyss && self.index(x, y).is_none()
+    }
+
+    // Drops a grain from the source and returns where it comes to rest. Returns `None` if the
+    // grain falls into the abyss, or if the source is already covered.
+    pub fn drop_grain(&mut self) -> Option<Point> {
+        let (mut x, mut y) = (SOURCE.x as i64, SOURCE.y as i64);
+        if self.is_blocked(x, y) {
+            return None;
+        }
+
+        while let Some(next) = self.next_position(x, y) {
+            (x, y) = next;
+            if self.in_abyss(x, y) {
+                return None;
+            }
+        }
+
+        self.set(x, y, Cell::Sand);
+        Some(Point {
+            x: x as u32,
+            y: y as u32,
+        })
+    }
+
+    // Drops grains until one falls into the abyss or the source is covered, and returns how many
+    // came to rest.
+    pub fn fill(&mut self) -> usize {
+        let mut grain_count = 0;
+        while self.drop_grain().is_some() {
+            grain_count += 1;
+        }
+        grain_count
+    }
+
+    // Same result as `fill`, but keeps the path of the last grain on a stack. The next grain
+    // follows the same path until the position where the last one came to rest, so it can start
+    // falling from the position just before that one. Every cell is then only visited a few times.
+    pub fn fill_with_path(&mut self) -> usize {
+        let mut grain_count = 0;
+        let source = (SOURCE.x as i64, SOURCE.y as i64);
+        if self.is_blocked(source.0, source.1) {
+            return grain_count;
+        }
+
+        let mut path = vec![source];
+        while let Some(&(x, y)) = path.last() {
+            match self.next_position(x, y) {
+                Some((x, y)) => {
+                    // Every grain after this one would follow the same path into the abyss.
+                    if self.in_abyss(x, y) {
+                        break;
+                    }
+                    path.push((x, y));
+                }
+                None => {
+                    self.set(x, y, Cell::Sand);
+                    grain_count += 1;
+                    path.pop();
+                }
+            }
+        }
+        grain_count
+    }
+
+    // Path the next grain would follow from the source, until it comes to rest or leaves the grid
+    // into the abyss.
+    pub fn fall_path(&self) -> Vec<(i64, i64)> {
+        let (mut x, mut y) = (SOURCE.x as i64, SOURCE.y as i64);
+        if self.is_blocked(x, y) {
+            return vec![];
+        }
+
+        let mut path = vec![(x, y)];
+        while let Some(next) = self.next_position(x, y) {
+            (x, y) = next;
+            if self.in_abyss(x, y) {
                 break;
             }
+            path.push((x, y));
+        }
+        path
+    }
 
-            // Can move directly down?
-            let down = Point {
-                x: grain.x,
-                y: grain.y + 1,
-            };
-            if !blocks.contains(&down) {
-                grain = down;
-                continue;
+    // Area shown when rendering: the whole grid, so grains rolling off the outermost walls are
+    // shown, plus the floor if there is one.
+    fn render_area(&self) -> (Range<i64>, Range<i64>) {
+        let bottom = match self.mode {
+            Mode::Abyss => self.height,
+            Mode::Floor { y } => y as usize + 1,
+        };
+        (self.left..self.left + self.width as i64, 0..bottom as i64)
+    }
+
+    // What to draw at a position, as in the puzzle: '+' for the source, '~' for the path of the
+    // falling grain, '#' for rock, 'o' for sand and '.' for air.
+    fn symbol_at(&self, x: i64, y: i64, falling: &HashSet<(i64, i64)>) -> char {
+        if (x, y) == (SOURCE.x as i64, SOURCE.y as i64) {
+            return '+';
+        }
+        match self.cell_at(x, y) {
+            Cell::Rock => '#',
+            Cell::Sand => 'o',
+            Cell::Air if falling.contains(&(x, y)) => '~',
+            Cell::Air => '.',
+        }
+    }
+
+    // Draws the cave, with `falling` as the path of a falling grain.
+    pub fn render(&self, falling: &[(i64, i64)]) -> String {
+        let falling = falling.iter().copied().collect::<HashSet<_>>();
+        let (columns, rows) = self.render_area();
+        let mut content = String::new();
+        for y in rows {
+            content.extend(columns.clone().map(|x| self.symbol_at(x, y, &falling)));
+            content.push('\n');
+        }
+        content
+    }
+
+    // Same as `render`, as a plain (P3) PPM image with one pixel per cell.
+    pub fn render_ppm(&self, falling: &[(i64, i64)]) -> String {
+        let falling = falling.iter().copied().collect::<HashSet<_>>();
+        let (columns, rows) = self.render_area();
+        let mut content = format!(
+            "P3\n{} {}\n255\n",
+            columns.clone().count(),
+            rows.clone().count()
+        );
+        for y in rows {
+            let pixels = columns
+                .clone()
+                .map(|x| match self.symbol_at(x, y, &falling) {
+                    '+' => "255 0 0",
+                    '~' => "64 128 255",
+                    '#' => "110 110 110",
+                    'o' => "230 190 90",
+                    _ => "20 20 30",
+                })
+                .collect::<Vec<_>>();
+            content.push_str(&pixels.join(" "));
+            content.push('\n');
+        }
+        content
+    }
+
+    pub fn sand_count(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|cell| **cell == Cell::Sand)
+            .count()
+    }
+}
+
+// The original simulation, storing rock and sand in a `HashSet` and dropping every grain from the
+// source. Kept as a reference for `Cave`.
+pub fn fill_with_hash_set(walls: &[Vec<Point>], mode: Mode) -> usize {
+    let bounds = Bounds::from_walls(walls);
+    let mut blocks = HashSet::new();
+    for wall in walls {
+        for (current, next) in wall.iter().zip(wall.iter().skip(1)) {
+            for x in u32::min(current.x, next.x)..=u32::max(current.x, next.x) {
+                blocks.insert(Point { x, y: current.y });
+            }
+            for y in u32::min(current.y, next.y)..=u32::max(current.y, next.y) {
+                blocks.insert(Point { x: current.x, y });
             }
+        }
+    }
 
-            let down_left = Point {
-                x: grain.x - 1,
-                y: grain.y + 1,
-            };
-            if !blocks.contains(&down_left) {
-                grain = down_left;
-                continue;
+    let mut grain_count = 0;
+    while !blocks.contains(&SOURCE) {
+        let mut grain = SOURCE;
+        loop {
+            match mode {
+                Mode::Abyss if grain.y >= bounds.max_y => return grain_count,
+                Mode::Floor { y } if grain.y + 1 == y => break,
+                _ => {}
             }
 
-            let down_right = Point {
-                x: grain.x + 1,
-                y: grain.y + 1,
-            };
-            if !blocks.contains(&down_right) {
-                grain = down_right;
-                continue;
+            let moved = [grain.x, grain.x - 1, grain.x + 1]
+                .into_iter()
+                .map(|x| Point { x, y: grain.y + 1 })
+                .find(|point| !blocks.contains(point));
+            match moved {
+                Some(point) => grain = point,
+                None => break,
             }
+        }
+        blocks.insert(grain);
+        grain_count += 1;
+    }
+    grain_count
+}
+
+fn main() {
+    // let input = SAMPLE_INPUT;
+    let input = include_str!("day14.txt");
+    let walls = parse_walls(input).unwrap();
+    let bounds = Bounds::from_walls(&walls);
+
+    let floor = Mode::Floor {
+        y: bounds.max_y + 2,
+    };
+
+    let mut cave = Cave::new(&walls, Mode::Abyss).unwrap();
+    println!("Part 1: {}", cave.fill_with_path());
+
+    let mut cave = Cave::new(&walls, floor).unwrap();
+    println!("Part 2: {}", cave.fill_with_path());
+
+    // Pass "--frames <n>" to print the part 1 cave every n grains, and "--ppm <path>" to write
+    // the filled part 2 cave as an image.
+    let args = std::env::args().collect::<Vec<_>>();
+    let arg_after = |flag: &str| {
+        let i = args.iter().position(|arg| arg == flag)?;
+        Some(
+            args.get(i + 1)
+                .unwrap_or_else(|| panic!("{} requires a value", flag)),
+        )
+    };
 
-            moved = false;
-            blocks.insert(grain);
+    if let Some(every) = arg_after("--frames") {
+        let every = every
+            .parse::<usize>()
+            .ok()
+            .filter(|every| *every > 0)
+            .expect("--frames requires a number above 0");
+        let mut cave = Cave::new(&walls, Mode::Abyss).unwrap();
+        let mut grain_count = 0;
+        while cave.drop_grain().is_some() {
+            grain_count += 1;
+            if grain_count % every == 0 {
+                println!("After {} grains:", grain_count);
+                println!("{}", cave.render(&cave.fall_path()));
+            }
         }
+        println!("Final, with {} grains:", grain_count);
+        println!("{}", cave.render(&cave.fall_path()));
+    }
 
-        // if grain.y >= max_y {
-        //     break;
-        // } else {
-        //     grain_count += 1;
-        // }
+    if let Some(path) = arg_after("--ppm") {
+        fs::write(path, cave.render_ppm(&[])).unwrap();
+    }
 
-        grain_count += 1;
-        if blocks.contains(&Point { x: 500, y: 0 }) {
-            break;
+    // Pass "--bench" to time the different ways of filling the cave.
+    if std::env::args().any(|arg| arg == "--bench") {
+        for mode in [Mode::Abyss, floor] {
+            let time = |name: &str, iterations: u32, fill: &dyn Fn() -> usize| {
+                let mut elapsed = Duration::ZERO;
+                let mut grain_count = 0;
+                for _ in 0..iterations {
+                    let start = Instant::now();
+                    grain_count = fill();
+                    elapsed += start.elapsed();
+                }
+                println!(
+                    "{:<20} {:>6} grains in {:>12?} ({:?})",
+                    name,
+                    grain_count,
+                    elapsed / iterations,
+                    mode
+                );
+            };
+
+            time("HashSet", 5, &|| fill_with_hash_set(&walls, mode));
+            time("Cave::fill", 20, &|| {
+                Cave::new(&walls, mode).unwrap().fill()
+            });
+            time("Cave::fill_with_path", 20, &|| {
+                Cave::new(&walls, mode).unwrap().fill_with_path()
+            });
         }
     }
-    println!("{:?}", grain_count);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_bounds() {
+        let walls = parse_walls(SAMPLE_INPUT).unwrap();
+        assert_eq!(
+            Bounds::from_walls(&walls),
+            Bounds {
+                min_x: 494,
+                max_x: 503,
+                min_y: 0,
+                max_y: 9
+            }
+        );
+    }
+
+    #[test]
+    fn fills_abyss_cave() {
+        let walls = parse_walls(SAMPLE_INPUT).unwrap();
+        let mut cave = Cave::new(&walls, Mode::Abyss).unwrap();
+        assert_eq!(cave.drop_grain(), Some(Point { x: 500, y: 8 }));
+        assert_eq!(cave.drop_grain(), Some(Point { x: 499, y: 8 }));
+        assert_eq!(cave.fill(), 22);
+        assert_eq!(cave.sand_count(), 24);
+    }
+
+    #[test]
+    fn fills_cave_with_floor() {
+        let walls = parse_walls(SAMPLE_INPUT).unwrap();
+        let mut cave = Cave::new(&walls, Mode::Floor { y: 11 }).unwrap();
+        assert_eq!(cave.fill(), 93);
+        assert!(cave.is_blocked(500, 0));
+    }
+
+    #[test]
+    fn rejects_invalid_walls() {
+        assert!(parse_walls("498,4 -> 498").is_err());
+        assert!(parse_walls("498,4 -> a,6").is_err());
+    }
+
+    #[test]
+    fn fills_with_path() {
+        let walls = parse_walls(SAMPLE_INPUT).unwrap();
+        for mode in [Mode::Abyss, Mode::Floor { y: 11 }] {
+            let mut cave = Cave::new(&walls, mode).unwrap();
+            let mut expected = cave.clone();
+            assert_eq!(cave.fill_with_path(), expected.fill());
+            assert_eq!(cave.cells, expected.cells);
+            assert_eq!(cave.fill_with_path(), 0);
+        }
+    }
+
+    #[test]
+    fn fills_with_hash_set() {
+        let walls = parse_walls(SAMPLE_INPUT).unwrap();
+        assert_eq!(fill_with_hash_set(&walls, Mode::Abyss), 24);
+        assert_eq!(fill_with_hash_set(&walls, Mode::Floor { y: 11 }), 93);
+    }
+
+    #[test]
+    fn renders_cave() {
+        let walls = parse_walls(SAMPLE_INPUT).unwrap();
+        let mut cave = Cave::new(&walls, Mode::Abyss).unwrap();
+        assert_eq!(
+            cave.render(&[]),
+            r".......+....
+............
+............
+............
+.....#...##.
+.....#...#..
+...###...#..
+.........#..
+.........#..
+.#########..
+"
+        );
+
+        cave.fill();
+        assert_eq!(
+            cave.render(&cave.fall_path()),
+            r".......+....
+.......~....
+......~o....
+.....~ooo...
+....~#ooo##.
+...~o#ooo#..
+..~###ooo#..
+..~..oooo#..
+.~o.ooooo#..
+~#########..
+"
+        );
+    }
+
+    #[test]
+    fn renders_cave_with_floor() {
+        let walls = parse_walls(SAMPLE_INPUT).unwrap();
+        let mut cave = Cave::new(&walls, Mode::Floor { y: 11 }).unwrap();
+        cave.fill();
+        let rendered = cave.render(&[]);
+        let lines = rendered.lines().collect::<Vec<_>>();
+        // The whole pile fits, from the source down to the floor.
+        assert_eq!(lines.len(), 12);
+        assert_eq!(lines[0].trim_matches('.'), "+");
+        assert_eq!(lines[10].trim_matches('.').len(), 21);
+        assert!(lines[11].chars().all(|c| c == '#'));
+        assert_eq!(rendered.matches('o').count() + 1, 93);
+    }
+
+    #[test]
+    fn renders_cave_ppm() {
+        let walls = parse_walls("499,2 -> 501,2").unwrap();
+        let mut cave = Cave::new(&walls, Mode::Abyss).unwrap();
+        cave.drop_grain();
+        assert_eq!(
+            cave.render_ppm(&[]),
+            "P3\n5 3\n255\n\
+             20 20 30 20 20 30 255 0 0 20 20 30 20 20 30\n\
+             20 20 30 20 20 30 230 190 90 20 20 30 20 20 30\n\
+             20 20 30 110 110 110 110 110 110 110 110 110 20 20 30\n"
+        );
+    }
+
+    #[test]
+    fn rejects_floor_above_walls() {
+        let walls = parse_walls(SAMPLE_INPUT).unwrap();
+        assert_eq!(
+            Cave::new(&walls, Mode::Floor { y: 5 }).unwrap_err(),
+            InvalidFloorError { floor: 5, max_y: 9 }
+        );
+        assert!(Cave::new(&walls, Mode::Floor { y: 9 }).is_err());
+        assert!(Cave::new(&walls, Mode::Floor { y: 10 }).is_ok());
+    }
 }
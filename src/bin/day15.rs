@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::{collections::HashSet, time::Instant};
 
 use aoc22::Point;
 use lazy_regex::{regex, Lazy, Regex};
@@ -50,6 +50,11 @@ impl Range {
             || (other.start <= self.end && other.start >= self.start)
     }
 
+    // Number of values in the range, including both ends.
+    pub fn size(&self) -> u64 {
+        self.start.abs_diff(self.end) + 1
+    }
+
     pub fn adjacent(&self, other: &Range) -> bool {
         other.start - self.end == 1 || self.start - other.end == 1
     }
@@ -134,73 +139,222 @@ impl Ranges {
         self.ranges.push(new);
         self.merge_ranges();
     }
+
+    // Number of values in all ranges. Ranges are merged, so no value is counted twice.
+    pub fn size(&self) -> u64 {
+        self.ranges.iter().map(Range::size).sum()
+    }
+
+    pub fn contains_value(&self, value: i64) -> bool {
+        self.ranges
+            .iter()
+            .any(|range| range.start <= value && value <= range.end)
+    }
 }
 
-fn compute_valid_points(
-    sensor_beacon_map: &HashMap<Point, Point>,
-    beacons: &HashSet<Point>,
-    target_y: i64,
-) -> HashSet<Point> {
-    let mut valid_points = HashSet::new();
-    for (sensor, beacon) in sensor_beacon_map {
-        let beacon_distance = sensor.manhattan_distance(beacon);
-        let middle = Point::new(sensor.x, target_y);
-        let middle_distance = sensor.manhattan_distance(&middle);
-
-        // Too far away...
-        if sensor.manhattan_distance(&middle) > beacon_distance {
-            continue;
+pub fn parse_sensors(input: &str) -> Vec<(Sensor, Point)> {
+    static REGEX: &Lazy<Regex> = regex!(r"x=(-?\d+), y=(-?\d+).+? x=(-?\d+), y=(-?\d+)");
+    input
+        .lines()
+        .map(|line| {
+            let result = REGEX.captures(line).unwrap();
+            let number = |i| result.get(i).unwrap().as_str().parse::<i64>().unwrap();
+            let sensor = Point::new(number(1), number(2));
+            let beacon = Point::new(number(3), number(4));
+            (
+                Sensor {
+                    point: sensor,
+                    range: sensor.manhattan_distance(&beacon),
+                },
+                beacon,
+            )
+        })
+        .collect()
+}
+
+// The sensors and the beacons they found.
+#[derive(Debug)]
+pub struct SensorField {
+    pub sensors: Vec<Sensor>,
+    pub beacons: HashSet<Point>,
+}
+
+impl SensorField {
+    pub fn new(readings: Vec<(Sensor, Point)>) -> Self {
+        let mut sensors = vec![];
+        let mut beacons = HashSet::new();
+        for (sensor, beacon) in readings {
+            sensors.push(sensor);
+            beacons.insert(beacon);
         }
+        Self { sensors, beacons }
+    }
 
-        let diff = middle_distance.abs_diff(beacon_distance) as i64;
-        for x in (middle.x - diff)..=(middle.x + diff) {
-            let point = Point::new(x, target_y);
-            if !beacons.contains(&point) {
-                valid_points.insert(point);
-            }
+    // The x positions covered by any sensor on row `y`.
+    pub fn coverage_at_row(&self, y: i64) -> Ranges {
+        let mut coverage = Ranges::default();
+        for sensor in &self.sensors {
+            // How much of the sensor range is left after reaching the row.
+            let Some(diff) = sensor.range.checked_sub(y.abs_diff(sensor.point.y)) else {
+                continue;
+            };
+            let diff = diff as i64;
+            coverage.extend(Range::new(sensor.point.x - diff, sensor.point.x + diff));
+        }
+        coverage
+    }
+
+    // Number of positions on row `y` where the distress beacon can't be: the ones covered by a
+    // sensor, except for beacons that were already found.
+    pub fn count_no_beacon_at_row(&self, y: i64) -> u64 {
+        let coverage = self.coverage_at_row(y);
+        let beacons = self
+            .beacons
+            .iter()
+            .filter(|beacon| beacon.y == y && coverage.contains_value(beacon.x))
+            .count();
+        coverage.size() - beacons as u64
+    }
+
+    // Draws the area between columns x0..=x1 and rows y0..=y1 like the puzzle does: 'S' for
+    // sensors, 'B' for beacons, '#' for positions covered by a sensor and '.' for the rest.
+    pub fn render(&self, x0: i64, x1: i64, y0: i64, y1: i64) -> String {
+        let sensors = self
+            .sensors
+            .iter()
+            .map(|sensor| sensor.point)
+            .collect::<HashSet<_>>();
+        let mut content = String::new();
+        for y in y0..=y1 {
+            content.extend((x0..=x1).map(|x| {
+                let point = Point::new(x, y);
+                if sensors.contains(&point) {
+                    'S'
+                } else if self.beacons.contains(&point) {
+                    'B'
+                } else if self.sensors.iter().any(|sensor| sensor.contains(&point)) {
+                    '#'
+                } else {
+                    '.'
+                }
+            }));
+            content.push('\n');
         }
+        content
+    }
+
+    // The only position in 0..=limit on both axes not covered by any sensor. Searches row by row
+    // if the boundary search comes up empty.
+    pub fn find_beacon(&self, limit: i64) -> Option<Point> {
+        find_beacon_by_boundaries(&self.sensors, limit)
+            .or_else(|| find_beacon_by_rows(&self.sensors, limit))
     }
-    valid_points
 }
 
-fn main() {
-    // let (input, target_y, part_2_limit) = (SAMPLE_INPUT, 10, 20);
-    let (input, target_y, part_2_limit) = (include_str!("day15.txt"), 2000000, 4000000);
-    static REGEX: &Lazy<Regex> = regex!(r"x=(-?\d+), y=(-?\d+).+? x=(-?\d+), y=(-?\d+)");
-    let mut sensor_beacon_map = HashMap::new();
-    let mut sensors = vec![];
-    let mut beacons = HashSet::new();
-
-    for line in input.lines() {
-        let result = REGEX.captures(line).unwrap();
-        let (sensor_x, sensor_y) = (
-            result.get(1).unwrap().as_str().parse::<i64>().unwrap(),
-            result.get(2).unwrap().as_str().parse::<i64>().unwrap(),
-        );
+pub fn tuning_frequency(point: &Point) -> i64 {
+    point.x * 4000000 + point.y
+}
 
-        let (beacon_x, beacon_y) = (
-            result.get(3).unwrap().as_str().parse::<i64>().unwrap(),
-            result.get(4).unwrap().as_str().parse::<i64>().unwrap(),
-        );
+// Finds the only point in 0..=limit on both axes that no sensor covers.
+//
+// Every neighbour of the point inside the search area is covered by some sensor, and one step
+// away from a covered point, the point must be just outside that sensor's range. So the point lies
+// on the boundary just outside some sensor's range, which is made of diagonal lines, either
+// `y = x + a` or `y = -x + b`. Each sensor covers one stretch of each line, so the point is found
+// by merging the stretches on every boundary line and looking for a gap inside the search area.
+// A single point search area has no neighbours, so it's checked on its own.
+pub fn find_beacon_by_boundaries(sensors: &[Sensor], limit: i64) -> Option<Point> {
+    let is_uncovered = |p: &Point| !sensors.iter().any(|sensor| sensor.contains(p));
+    if limit == 0 {
+        return Some(Point::new(0, 0)).filter(is_uncovered);
+    }
+
+    let mut lines = vec![];
+    for sensor in sensors {
+        let (x, y) = (sensor.point.x, sensor.point.y);
+        let distance = sensor.range as i64 + 1;
+        lines.extend([
+            Diagonal::Rising(y - x - distance),
+            Diagonal::Rising(y - x + distance),
+            Diagonal::Falling(y + x - distance),
+            Diagonal::Falling(y + x + distance),
+        ]);
+    }
+
+    lines.into_iter().find_map(|line| {
+        let (start, end) = line.x_range(limit);
+        let mut coverage = Ranges::default();
+        for sensor in sensors {
+            if let Some(range) = line.covered_by(sensor) {
+                coverage.extend(range);
+            }
+        }
+
+        // First x in start..=end outside every covered range. Merged ranges are sorted.
+        let mut x = start;
+        for range in &coverage.ranges {
+            if range.end < x {
+                continue;
+            }
+            if range.start > x {
+                break;
+            }
+            x = range.end + 1;
+        }
+        (x <= end).then(|| line.point_at(x))
+    })
+}
+
+// A diagonal line, either `y = x + a` or `y = -x + b`.
+#[derive(Copy, Clone, Debug)]
+enum Diagonal {
+    Rising(i64),
+    Falling(i64),
+}
+
+impl Diagonal {
+    fn point_at(&self, x: i64) -> Point {
+        match self {
+            Diagonal::Rising(a) => Point::new(x, x + a),
+            Diagonal::Falling(b) => Point::new(x, b - x),
+        }
+    }
 
-        let sensor = Point::new(sensor_x, sensor_y);
-        let beacon = Point::new(beacon_x, beacon_y);
-        beacons.insert(beacon.clone());
-        sensors.push(Sensor {
-            point: sensor,
-            range: sensor.manhattan_distance(&beacon),
-        });
-        sensor_beacon_map.insert(sensor, beacon);
+    // Values of x where the line is inside 0..=limit on both axes. Empty if start > end.
+    fn x_range(&self, limit: i64) -> (i64, i64) {
+        match self {
+            Diagonal::Rising(a) => (i64::max(0, -a), i64::min(limit, limit - a)),
+            Diagonal::Falling(b) => (i64::max(0, b - limit), i64::min(limit, *b)),
+        }
     }
 
-    let valid_points = compute_valid_points(&sensor_beacon_map, &beacons, target_y);
-    println!("Part 1: {}", valid_points.len());
+    // Values of x where the line is in range of `sensor`. Going `u` along x from the sensor, the
+    // distance to the line is `|u| + |u + c|`, which is the same as `max(|2u + c|, |c|)`, so
+    // the covered values are a single range.
+    fn covered_by(&self, sensor: &Sensor) -> Option<Range> {
+        let (x, y, range) = (sensor.point.x, sensor.point.y, sensor.range as i64);
+        let c = match self {
+            Diagonal::Rising(a) => a + x - y,
+            Diagonal::Falling(b) => x + y - b,
+        };
+        if c.abs() > range {
+            return None;
+        }
 
-    let mut all_ranges = vec![Ranges::default(); part_2_limit + 1];
-    for sensor in &sensors {
+        // -range <= 2u + c <= range, rounding inwards.
+        let start = -((range + c).div_euclid(2));
+        let end = (range - c).div_euclid(2);
+        Some(Range::new(x + start, x + end))
+    }
+}
+
+// Same as `find_beacon_by_boundaries`, by merging the ranges each sensor covers on every row of the
+// search area, and looking for the row with a gap. Much slower, and needs memory for every row.
+pub fn find_beacon_by_rows(sensors: &[Sensor], limit: i64) -> Option<Point> {
+    let mut all_ranges = vec![Ranges::default(); limit as usize + 1];
+    for sensor in sensors {
         let start_y = i64::max(0, sensor.point.y - sensor.range as i64);
-        let end_y = i64::min(sensor.point.y + sensor.range as i64, part_2_limit as i64);
-        // println!("{:?} => start: {}, end: {}", sensor, start_y, end_y);
+        let end_y = i64::min(sensor.point.y + sensor.range as i64, limit);
         for y in start_y..=end_y {
             // Difference between current y and sensor y.
             let y_distance = y.abs_diff(sensor.point.y);
@@ -208,44 +362,57 @@ fn main() {
             let diff = sensor.range.abs_diff(y_distance) as i64;
 
             let start_x = i64::max(0, sensor.point.x - diff);
-            let end_x = i64::min(part_2_limit as i64, sensor.point.x + diff);
+            let end_x = i64::min(limit, sensor.point.x + diff);
             let range = Range::new(start_x, end_x);
             all_ranges[y as usize].extend(range);
         }
     }
 
-    let (y, r) = all_ranges
-        .iter()
-        .enumerate()
-        .find(|(_, r)| r.ranges.len() > 1)
-        .unwrap();
-    println!("range: {:?}, y: {}", r, y);
-    let freq = (r.ranges[0].end + 1) * 4000000 + y as i64;
-    println!("Part 2: {}", freq);
-
-    // println!("{}", sensors.len());
-    // for y in 0..=part_2_limit {
-    //     if y % 100 == 0 {
-    //        println!("Y: {}", y);
-    //     }
-
-    //     for x in 0..=part_2_limit {
-    //         let point = Point::new(x, y);
-
-    //         let mut in_range = false;
-    //         for sensor in &sensors {
-    //             if sensor.contains(&point) {
-    //                 in_range = true;
-    //                 // println!("{:?} contains {:?}", sensor, point);
-    //                 break;
-    //             }
-    //         }
-    //         if !in_range {
-    //             println!("{:?} => {}", point, point.x * 4000000 + y);
-    //             break;
-    //         }
-    //     }
-    // }
+    all_ranges.iter().enumerate().find_map(|(y, r)| {
+        let x = match r.ranges.first() {
+            None => 0,
+            Some(first) if first.start > 0 => 0,
+            Some(first) if first.end < limit => first.end + 1,
+            Some(_) => return None,
+        };
+        Some(Point::new(x, y as i64))
+    })
+}
+
+fn main() {
+    // Pass "--sample" to run on the sample input.
+    let args = std::env::args().collect::<Vec<_>>();
+    let (input, target_y, part_2_limit) = if args.iter().any(|arg| arg == "--sample") {
+        (SAMPLE_INPUT, 10, 20)
+    } else {
+        (include_str!("day15.txt"), 2000000, 4000000)
+    };
+    let field = SensorField::new(parse_sensors(input));
+    println!("Part 1: {}", field.count_no_beacon_at_row(target_y));
+
+    // Pass "--render <x0> <x1> <y0> <y1>" to draw that area of the sensor field.
+    if let Some(i) = args.iter().position(|arg| arg == "--render") {
+        let bounds = args[i + 1..]
+            .iter()
+            .take(4)
+            .map(|arg| arg.parse::<i64>().expect("--render requires numbers"))
+            .collect::<Vec<_>>();
+        let [x0, x1, y0, y1] = bounds[..] else {
+            panic!("--render requires x0, x1, y0 and y1");
+        };
+        print!("{}", field.render(x0, x1, y0, y1));
+    }
+
+    // Pass "--rows" to search for the beacon row by row instead.
+    let start = Instant::now();
+    let beacon = if args.iter().any(|arg| arg == "--rows") {
+        find_beacon_by_rows(&field.sensors, part_2_limit)
+    } else {
+        field.find_beacon(part_2_limit)
+    }
+    .unwrap();
+    println!("beacon: {:?}, found in {:?}", beacon, start.elapsed());
+    println!("Part 2: {}", tuning_frequency(&beacon));
 }
 
 #[cfg(test)]
@@ -315,4 +482,137 @@ mod tests {
         assert_eq!(ranges.ranges[0].start, 0);
         assert_eq!(ranges.ranges[0].end, 20);
     }
+
+    #[test]
+    fn finds_beacon() {
+        let sensors = parse_sensors(SAMPLE_INPUT)
+            .into_iter()
+            .map(|(sensor, _)| sensor)
+            .collect::<Vec<_>>();
+        let beacon = Point::new(14, 11);
+        assert_eq!(find_beacon_by_boundaries(&sensors, 20), Some(beacon));
+        assert_eq!(find_beacon_by_rows(&sensors, 20), Some(beacon));
+        assert_eq!(tuning_frequency(&beacon), 56000011);
+    }
+
+    #[test]
+    fn finds_beacon_in_corner() {
+        let sensors = [Sensor {
+            point: Point::new(10, 10),
+            range: 19,
+        }];
+        let expected = Some(Point::new(0, 0));
+        assert_eq!(find_beacon_by_boundaries(&sensors, 10), expected);
+        assert_eq!(find_beacon_by_rows(&sensors, 10), expected);
+    }
+
+    #[test]
+    fn finds_beacon_on_edge() {
+        let sensor = |x, y, range| Sensor {
+            point: Point::new(x, y),
+            range,
+        };
+        let sensors = [sensor(-3, 4, 7), sensor(-2, -1, 4), sensor(3, 1, 1)];
+        let expected = Some(Point::new(2, 0));
+        assert_eq!(find_beacon_by_boundaries(&sensors, 3), expected);
+        assert_eq!(find_beacon_by_rows(&sensors, 3), expected);
+    }
+
+    #[test]
+    fn finds_beacon_in_small_fields() {
+        // Checks many small fields with exactly one uncovered point against a brute force search.
+        // The fields come from a simple linear congruential generator, so they're the same on
+        // every run.
+        let mut seed = 12345u64;
+        let mut random = |max: i64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as i64 % max
+        };
+
+        let limit = 6;
+        let mut checked = 0;
+        while checked < 500 {
+            let sensors = (0..1 + random(5))
+                .map(|_| Sensor {
+                    point: Point::new(random(12) - 3, random(12) - 3),
+                    range: random(7) as u64,
+                })
+                .collect::<Vec<_>>();
+            let uncovered = (0..=limit)
+                .flat_map(|y| (0..=limit).map(move |x| Point::new(x, y)))
+                .filter(|p| !sensors.iter().any(|sensor| sensor.contains(p)))
+                .collect::<Vec<_>>();
+            if uncovered.len() != 1 {
+                continue;
+            }
+
+            checked += 1;
+            assert_eq!(
+                find_beacon_by_boundaries(&sensors, limit),
+                Some(uncovered[0]),
+                "{:?}",
+                sensors
+            );
+        }
+    }
+
+    #[test]
+    fn computes_coverage_at_row() {
+        let field = SensorField::new(parse_sensors(SAMPLE_INPUT));
+        let coverage = field.coverage_at_row(10);
+        assert_eq!(coverage.ranges, vec![Range::new(-2, 24)]);
+        assert_eq!(coverage.size(), 27);
+        assert_eq!(field.count_no_beacon_at_row(10), 26);
+
+        let coverage = field.coverage_at_row(11);
+        assert_eq!(
+            coverage.ranges,
+            vec![Range::new(-3, 13), Range::new(15, 25)]
+        );
+        assert!(!coverage.contains_value(14));
+        assert_eq!(field.count_no_beacon_at_row(11), 28);
+
+        assert_eq!(field.coverage_at_row(-100).size(), 0);
+        assert_eq!(field.find_beacon(20), Some(Point::new(14, 11)));
+    }
+
+    #[test]
+    fn renders_sensor_field() {
+        let field = SensorField::new(vec![(
+            Sensor {
+                point: Point::new(0, 0),
+                range: 2,
+            },
+            Point::new(1, 1),
+        )]);
+        assert_eq!(
+            field.render(-3, 3, -3, 3),
+            r".......
+...#...
+..###..
+.##S##.
+..##B..
+...#...
+.......
+"
+        );
+    }
+
+    #[test]
+    fn renders_coverage_at_row() {
+        let field = SensorField::new(parse_sensors(SAMPLE_INPUT));
+        let (x0, x1) = (-8, 32);
+        for y in -10..=30 {
+            let rendered = field.render(x0, x1, y, y);
+            let covered = rendered.chars().filter(|c| *c != '.' && *c != '\n');
+            let expected = (x0..=x1).filter(|x| field.coverage_at_row(y).contains_value(*x));
+            assert_eq!(covered.count(), expected.count(), "row {}", y);
+        }
+        assert_eq!(
+            field.render(-2, 25, 10, 10),
+            "####B######################.\n"
+        );
+    }
 }
@@ -1,10 +1,7 @@
 use lazy_regex::{regex, Lazy, Regex};
-use std::{
-    collections::{HashMap, HashSet},
-    io::Write,
-    str::FromStr,
-};
+use std::{collections::HashMap, str::FromStr};
 
+#[allow(dead_code)]
 const SAMPLE_INPUT: &str = r"Valve AA has flow rate=0; tunnels lead to valves DD, II, BB
 Valve BB has flow rate=13; tunnels lead to valves CC, AA
 Valve CC has flow rate=2; tunnels lead to valves DD, BB
@@ -61,239 +58,478 @@ impl FromStr for Valve {
     }
 }
 
-// Calculates the best path from the last item in the path provided as parameter to the
-// destination.
-fn calculate_path<'a>(
-    valves: &'a HashMap<String, Valve>,
-    destination: &str,
-    path: &[&'a str],
-) -> Vec<&'a str> {
-    let Some(last) = path.last() else {
-        panic!("Can't start with an empty path :)");
-    };
-
-    let last = *last;
-    if last == destination {
-        return path.to_vec();
-    }
+pub fn parse_valves(input: &str) -> Result<Vec<Valve>, ParseInputError> {
+    input.lines().map(str::parse).collect()
+}
 
-    let valve = valves.get(last).expect("Unknown Valve");
+// The valves, with their IDs mapped to dense indices, and the length of the shortest path between
+// every pair of them. `distances[a][b]` is `UNREACHABLE` if there's no path from `a` to `b`.
+#[derive(Clone, Debug)]
+pub struct ValveGraph {
+    pub ids: Vec<String>,
+    pub flow_rates: Vec<u32>,
+    pub distances: Vec<Vec<u32>>,
+}
 
-    let mut best_path = vec![];
-    for tunnel in &valve.tunnels {
-        if !path.contains(&tunnel.as_str()) {
-            let mut next_path = path.to_vec();
-            next_path.push(tunnel.as_str());
-            let new_path = calculate_path(valves, destination, &next_path);
-            if best_path.is_empty() || (!new_path.is_empty() && new_path.len() < best_path.len()) {
-                best_path = new_path;
+pub const UNREACHABLE: u32 = u32::MAX;
+
+impl ValveGraph {
+    // Builds the graph and computes all-pairs distances with Floyd-Warshall. Fails if a tunnel
+    // leads to an unknown valve.
+    pub fn new(valves: &[Valve]) -> Result<Self, ParseInputError> {
+        let indices = valves
+            .iter()
+            .enumerate()
+            .map(|(i, valve)| (valve.id.as_str(), i))
+            .collect::<HashMap<_, _>>();
+
+        let n = valves.len();
+        let mut distances = vec![vec![UNREACHABLE; n]; n];
+        for (a, valve) in valves.iter().enumerate() {
+            distances[a][a] = 0;
+            for tunnel in &valve.tunnels {
+                let b = *indices.get(tunnel.as_str()).ok_or(ParseInputError)?;
+                distances[a][b] = 1;
             }
         }
+
+        for k in 0..n {
+            for a in 0..n {
+                for b in 0..n {
+                    let through_k = distances[a][k].saturating_add(distances[k][b]);
+                    if through_k < distances[a][b] {
+                        distances[a][b] = through_k;
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            ids: valves.iter().map(|valve| valve.id.clone()).collect(),
+            flow_rates: valves.iter().map(|valve| valve.flow_rate).collect(),
+            distances,
+        })
     }
 
-    best_path
-}
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
 
-// Implements Part 1.
-fn find_best_path<'a>(
-    valves: &'a HashMap<String, Valve>,
-    closed_valves: &HashSet<&'a str>,
-    current_valve: &'a str,
-    paths: &HashMap<&str, HashMap<&str, Vec<&str>>>,
-    total_pressure: u32,
-    minutes_left: u32,
-) -> u32 {
-    let total_pressure =
-        total_pressure + minutes_left * valves.get(current_valve).unwrap().flow_rate;
-    let minutes_left = minutes_left - 1;
-    let closed_valves = closed_valves
-        .iter()
-        .filter(|v| **v != current_valve)
-        .map(|v| *v)
-        .collect::<HashSet<_>>();
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
 
-    if minutes_left == 0 || closed_valves.is_empty() {
-        return total_pressure;
+    // Indices of the valves worth opening. Sets of opened valves are bitmasks over this list, with
+    // bit i standing for the i-th valve in it, so valves that can't be opened don't take up bits.
+    pub fn openable_valves(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|&i| self.flow_rates[i] > 0)
+            .collect()
     }
 
-    let from_current_valve = paths.get(current_valve).unwrap();
-    let mut highest_pressure = total_pressure;
-    for valve in &closed_valves {
-        let path = from_current_valve.get(valve).unwrap();
-        let minutes_left = minutes_left as i32 - (path.len() as i32 - 1);
-        if minutes_left < 1 {
-            // Not enough time left to open the valve. Skip it.
-            continue;
-        };
-        let pressure = find_best_path(
-            valves,
-            &closed_valves,
-            valve,
-            paths,
-            total_pressure,
-            minutes_left as u32,
-        );
+    pub fn index_of(&self, id: &str) -> Option<usize> {
+        self.ids.iter().position(|other| other == id)
+    }
 
-        if pressure > highest_pressure {
-            highest_pressure = pressure;
+    // Keeps only `start` and the valves worth opening. Valves with no flow are only ever walked
+    // through, and the distances already account for them. `start` is always at index 0.
+    pub fn compress(&self, start: &str) -> Option<ValveGraph> {
+        self.compress_from(&[start])
+    }
+
+    // Same as `compress`, keeping every valve in `starts`, in that order, before the others.
+    pub fn compress_from(&self, starts: &[&str]) -> Option<ValveGraph> {
+        let mut kept = vec![];
+        for start in starts {
+            let start = self.index_of(start)?;
+            if !kept.contains(&start) {
+                kept.push(start);
+            }
         }
+        let others = (0..self.len()).filter(|i| !kept.contains(i) && self.flow_rates[*i] > 0);
+        kept.extend(others.collect::<Vec<_>>());
+
+        Some(ValveGraph {
+            ids: kept.iter().map(|&i| self.ids[i].clone()).collect(),
+            flow_rates: kept.iter().map(|&i| self.flow_rates[i]).collect(),
+            distances: kept
+                .iter()
+                .map(|&a| kept.iter().map(|&b| self.distances[a][b]).collect())
+                .collect(),
+        })
     }
+}
 
-    highest_pressure
+// A search state: where we are and which valves are open, as a bitmask over the openable valves.
+type State = (usize, usize);
+
+// States reached with the same minutes left, each with its best pressure and the state it was
+// reached from, with that state's minutes left.
+type Layer = HashMap<State, (u32, Option<(u32, State)>)>;
+
+// All the ways of opening valves in `minutes` starting from `start`.
+//
+// States are kept per minutes left, with the best pressure for each and the state it was reached
+// from. Each minute's states only lead to states with fewer minutes left, so they're processed
+// from the most minutes left to the fewest.
+struct Exploration {
+    minutes: u32,
+    states: Vec<Layer>,
+    // For each set of opened valves, the best pressure and the state that got it.
+    best: Vec<Option<(u32, u32, State)>>,
 }
 
-// Implements Part 2. Information for me is prefixed with "m". Information for the elephant is
-// prefixed with "e".
-fn find_best_path_with_elephant<'a>(
-    valves: &'a HashMap<String, Valve>,
-    mut closed_valves: HashSet<&'a str>,
-    m_current_valve: &'a str,
-    e_current_valve: &'a str,
-    paths: &HashMap<&str, HashMap<&str, Vec<&str>>>,
-    mut m_total_pressure: u32,
-    mut e_total_pressure: u32,
-    mut m_minutes_left: u32,
-    mut e_minutes_left: u32,
-    depth: u32,
-) -> u32 {
-    // Open valves if there's enough time left.
-    if m_minutes_left > 0 && closed_valves.contains(&m_current_valve) {
-        m_minutes_left = m_minutes_left - 1;
-        m_total_pressure =
-            m_total_pressure + m_minutes_left * valves.get(m_current_valve).unwrap().flow_rate;
-        closed_valves.remove(&m_current_valve);
+impl Exploration {
+    fn new(graph: &ValveGraph, start: usize, minutes: u32) -> Self {
+        let valves = graph.openable_valves();
+        let mut best = vec![None; 1 << valves.len()];
+        let mut states = vec![Layer::new(); minutes as usize + 1];
+        states[minutes as usize].insert((start, 0), (0, None));
+
+        for minutes_left in (0..=minutes).rev() {
+            let (later, current) = states.split_at_mut(minutes_left as usize);
+            for (&(position, opened), &(pressure, _)) in &current[0] {
+                if best[opened].is_none_or(|(best, _, _)| pressure > best) {
+                    best[opened] = Some((pressure, minutes_left, (position, opened)));
+                }
+
+                for (bit, &valve) in valves.iter().enumerate() {
+                    if opened & (1 << bit) != 0 {
+                        continue;
+                    }
+
+                    // Time to walk to the valve and open it.
+                    let cost = graph.distances[position][valve].saturating_add(1);
+                    if cost >= minutes_left {
+                        continue;
+                    }
+
+                    let next_minutes_left = minutes_left - cost;
+                    let next = (
+                        pressure + next_minutes_left * graph.flow_rates[valve],
+                        Some((minutes_left, (position, opened))),
+                    );
+                    let entry = later[next_minutes_left as usize]
+                        .entry((valve, opened | (1 << bit)))
+                        .or_insert(next);
+                    if next.0 > entry.0 {
+                        *entry = next;
+                    }
+                }
+            }
+        }
+
+        Self {
+            minutes,
+            states,
+            best,
+        }
     }
 
-    if e_minutes_left > 0 && closed_valves.contains(&e_current_valve) {
-        e_minutes_left = e_minutes_left - 1;
-        e_total_pressure =
-            e_total_pressure + e_minutes_left * valves.get(e_current_valve).unwrap().flow_rate;
-        closed_valves.remove(&e_current_valve);
+    // The valves opened to get the best pressure for `opened`, with the minute each was opened on.
+    fn schedule(&self, opened: usize) -> Option<Vec<(u32, usize)>> {
+        let (_, mut minutes_left, mut state) = self.best[opened]?;
+        let mut schedule = vec![];
+        while let Some(parent) = self.states[minutes_left as usize][&state].1 {
+            schedule.push((self.minutes - minutes_left, state.0));
+            (minutes_left, state) = parent;
+        }
+        schedule.reverse();
+        Some(schedule)
     }
+}
 
-    // Total pressure released so far.
-    let mut overall_total_pressure = m_total_pressure + e_total_pressure;
+// Best pressure released in `minutes` starting from `start`, for every set of opened valves.
+// `best[set]` is the best pressure when only the valves in `set`, a bitmask over
+// `ValveGraph::openable_valves`, are opened. Sets that can't be opened in time get 0.
+pub fn best_pressures(graph: &ValveGraph, start: usize, minutes: u32) -> Vec<u32> {
+    Exploration::new(graph, start, minutes)
+        .best
+        .into_iter()
+        .map(|best| best.map_or(0, |(pressure, _, _)| pressure))
+        .collect()
+}
 
-    // At least 2 minutes are needed to continue - at least 1 to move to the next closed valve and
-    // another to open it. If we don't have this time for myself and the elephant, the search is over.
-    // It's also over if there are no more closed valves.
-    if (m_minutes_left < 2 && e_minutes_left < 2) || closed_valves.is_empty() {
-        return overall_total_pressure;
-    }
+// Implements Part 1.
+pub fn max_pressure(graph: &ValveGraph, start: usize, minutes: u32) -> u32 {
+    best_pressures(graph, start, minutes)
+        .into_iter()
+        .max()
+        .unwrap_or(0)
+}
 
-    let from_m_current_valve = paths.get(m_current_valve).unwrap();
-    let from_e_current_valve = paths.get(e_current_valve).unwrap();
-
-    let total = closed_valves.len() * closed_valves.len();
-    let mut progress = 0;
-    for m_valve in &closed_valves {
-        let m_path = from_m_current_valve.get(m_valve).unwrap();
-        let m_minutes_left = (m_minutes_left as i32 - (m_path.len() as i32 - 1)).max(0) as u32;
-
-        for e_valve in &closed_valves {
-            progress += 1;
-            if depth == 0 {
-                print!(
-                    "\r{} of {} => {}% - {}        ",
-                    progress,
-                    total,
-                    progress * 100 / total,
-                    overall_total_pressure
-                );
-                std::io::stdout().flush().unwrap();
+// Implements Part 2. Me and the elephant never need to open the same valve, so the best plan is the
+// best pair of disjoint sets of valves, one for each of us.
+pub fn max_pressure_with_elephant(graph: &ValveGraph, start: usize, minutes: u32) -> u32 {
+    let mut best = best_pressures(graph, start, minutes);
+
+    // Make `best[set]` the best for any subset of `set`, so each set only has to be paired with
+    // its complement.
+    for bit in 0..best.len().trailing_zeros() {
+        for set in 0..best.len() {
+            if set & (1 << bit) != 0 {
+                best[set] = u32::max(best[set], best[set ^ (1 << bit)]);
             }
+        }
+    }
 
-            // Technically, this should be fine, but we can avoid one iteration.
-            if m_valve == e_valve {
-                continue;
-            }
+    let all = best.len() - 1;
+    (0..best.len())
+        .map(|set| best[set] + best[all ^ set])
+        .max()
+        .unwrap_or(0)
+}
+
+// Someone opening valves, starting at `start` with `minutes` to do it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Agent {
+    pub start: String,
+    pub minutes: u32,
+}
+
+impl FromStr for Agent {
+    type Err = ParseInputError;
+
+    // Parses "<start>:<minutes>", like "AA:26".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, minutes) = s.split_once(':').ok_or(ParseInputError)?;
+        Ok(Self {
+            start: start.to_string(),
+            minutes: minutes.parse().map_err(|_| ParseInputError)?,
+        })
+    }
+}
 
-            let e_path = from_e_current_valve.get(e_valve).unwrap();
-            let e_minutes_left = (e_minutes_left as i32 - (e_path.len() as i32 - 1)).max(0) as u32;
-
-            let pressure = find_best_path_with_elephant(
-                valves,
-                closed_valves.clone(),
-                m_valve,
-                e_valve,
-                paths,
-                m_total_pressure,
-                e_total_pressure,
-                m_minutes_left as u32,
-                e_minutes_left as u32,
-                depth + 1,
-            );
-
-            // Store the new best pressure, if this path is better.s
-            if pressure > overall_total_pressure {
-                overall_total_pressure = pressure;
+// The best plan for a group of agents: the total pressure released, and for each agent the valves
+// it opens with the minute it opens them on.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Plan {
+    pub pressure: u32,
+    pub schedules: Vec<Vec<(u32, String)>>,
+}
+
+// Finds the best plan for any number of agents. No two agents need to open the same valve, so the
+// plan splits the valves into disjoint sets, one per agent. Agents are added one at a time: for
+// every set of valves, the best total is the best over all ways of giving one subset to the new
+// agent and the rest to the agents before it. Returns `None` if an agent starts on an unknown
+// valve.
+pub fn plan_agents(graph: &ValveGraph, agents: &[Agent]) -> Option<Plan> {
+    let starts = agents
+        .iter()
+        .map(|agent| agent.start.as_str())
+        .collect::<Vec<_>>();
+    let graph = graph.compress_from(&starts)?;
+    let all = (1 << graph.openable_valves().len()) - 1;
+
+    // `totals[set]` is the best pressure for the agents so far, opening valves in `set` only.
+    // `choices[i][set]` is the set agent i opens to get `totals[set]` after it.
+    let mut totals = vec![0; all + 1];
+    let mut explorations = vec![];
+    let mut choices = vec![];
+    for agent in agents {
+        let start = graph.index_of(&agent.start)?;
+        let exploration = Exploration::new(&graph, start, agent.minutes);
+        let mut next_totals = vec![0; all + 1];
+        let mut choice = vec![0; all + 1];
+        for set in 0..=all {
+            // Goes through every subset of `set`, including the empty one.
+            let mut subset = set;
+            loop {
+                if let Some((pressure, _, _)) = exploration.best[subset] {
+                    let total = totals[set ^ subset] + pressure;
+                    if total > next_totals[set] {
+                        next_totals[set] = total;
+                        choice[set] = subset;
+                    }
+                }
+                if subset == 0 {
+                    break;
+                }
+                subset = (subset - 1) & set;
             }
         }
+        totals = next_totals;
+        explorations.push(exploration);
+        choices.push(choice);
     }
 
-    overall_total_pressure
+    let mut schedules = vec![];
+    let mut set = all;
+    for (exploration, choice) in explorations.iter().zip(&choices).rev() {
+        let schedule = exploration.schedule(choice[set])?;
+        schedules.push(
+            schedule
+                .into_iter()
+                .map(|(minute, valve)| (minute, graph.ids[valve].clone()))
+                .collect(),
+        );
+        set ^= choice[set];
+    }
+    schedules.reverse();
+
+    Some(Plan {
+        pressure: totals[all],
+        schedules,
+    })
 }
 
 fn main() {
     // let input = SAMPLE_INPUT;
     let input = include_str!("day16.txt");
 
-    let mut valves: HashMap<String, Valve> = HashMap::new();
+    let valves = parse_valves(input).unwrap();
+    let graph = ValveGraph::new(&valves).unwrap();
+
+    // Only "AA", where we start, and the valves that can be opened are needed to find the best
+    // order to open valves in. "AA" is always at index 0.
+    let graph = graph.compress("AA").expect("There's no valve AA");
+    println!("Found {} openable valves", graph.len() - 1);
+
+    println!("Part1: {:?}", max_pressure(&graph, 0, 30));
+    println!("Part 2: {}", max_pressure_with_elephant(&graph, 0, 26));
+
+    // Pass "--agents <start>:<minutes> ..." to plan for any group, like "--agents AA:26 AA:26
+    // AA:26" for me and two elephants.
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(i) = args.iter().position(|arg| arg == "--agents") {
+        let agents = args[i + 1..]
+            .iter()
+            .map(|arg| arg.parse::<Agent>().expect("Agents look like AA:26"))
+            .collect::<Vec<_>>();
+        let full_graph = ValveGraph::new(&valves).unwrap();
+        let plan = plan_agents(&full_graph, &agents).expect("Unknown start valve");
+        println!("Total pressure: {}", plan.pressure);
+        for (agent, schedule) in agents.iter().zip(&plan.schedules) {
+            let opened = schedule
+                .iter()
+                .map(|(minute, valve)| format!("{} at minute {}", valve, minute))
+                .collect::<Vec<_>>();
+            println!("{}:{} => {}", agent.start, agent.minutes, opened.join(", "));
+        }
+    }
+}
 
-    for line in input.lines() {
-        let valve = line.parse::<Valve>().unwrap();
-        valves.insert(valve.id.clone(), valve);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn computes_distances() {
+        let valves = parse_valves(SAMPLE_INPUT).unwrap();
+        let graph = ValveGraph::new(&valves).unwrap();
+        let distance = |a: &str, b: &str| {
+            graph.distances[graph.index_of(a).unwrap()][graph.index_of(b).unwrap()]
+        };
+        assert_eq!(distance("AA", "AA"), 0);
+        assert_eq!(distance("AA", "DD"), 1);
+        assert_eq!(distance("AA", "HH"), 5);
+        assert_eq!(distance("JJ", "HH"), 7);
+        assert_eq!(distance("HH", "JJ"), 7);
     }
 
-    // Valves that can be opened.
-    let openable_valves = valves
-        .values()
-        .filter(|v| v.flow_rate > 0)
-        .map(|v| v.id.as_str())
-        .collect::<HashSet<_>>();
-
-    println!("Found {} openable valves", openable_valves.len());
-
-    // Pre-compute the paths between valves. The only valve that's not openable that we need as an
-    // origin is point is "AA", but including all of them. Only using openable valves as destinations.
-    let mut paths: HashMap<&str, HashMap<&str, Vec<&str>>> = HashMap::new();
-    for a in valves.keys() {
-        for b in valves.keys() {
-            let valve = valves.get(b).unwrap();
-            if valve.flow_rate == 0 {
-                continue;
-            }
+    #[test]
+    fn compresses_graph() {
+        let valves = parse_valves(SAMPLE_INPUT).unwrap();
+        let graph = ValveGraph::new(&valves).unwrap().compress("AA").unwrap();
+        assert_eq!(graph.ids, vec!["AA", "BB", "CC", "DD", "EE", "HH", "JJ"]);
+        assert_eq!(graph.flow_rates, vec![0, 13, 2, 20, 3, 22, 21]);
+        assert_eq!(graph.distances[0], vec![0, 1, 2, 1, 2, 5, 2]);
+        assert!(ValveGraph::new(&valves).unwrap().compress("ZZ").is_none());
+    }
 
-            let best_path = calculate_path(&valves, b, &[a]);
-            paths
-                .entry(a)
-                .or_insert(HashMap::default())
-                .insert(b, best_path);
-        }
+    #[test]
+    fn rejects_unknown_tunnels() {
+        let valves = parse_valves("Valve AA has flow rate=0; tunnel leads to valve BB").unwrap();
+        assert!(ValveGraph::new(&valves).is_err());
+    }
+
+    #[test]
+    fn finds_max_pressure() {
+        let valves = parse_valves(SAMPLE_INPUT).unwrap();
+        let graph = ValveGraph::new(&valves).unwrap().compress("AA").unwrap();
+        assert_eq!(max_pressure(&graph, 0, 30), 1651);
+        assert_eq!(max_pressure_with_elephant(&graph, 0, 26), 1707);
     }
 
-    // Filter the list of valves with the ones that are openable.
-    let closed_valves = valves
-        .values()
-        .filter(|v| v.flow_rate > 0)
-        .map(|v| v.id.as_str())
-        .collect::<HashSet<_>>();
-
-    let result = find_best_path(&valves, &closed_valves, "AA", &paths, 0, 30);
-    println!("Part1: {:?}", result);
-
-    let result = find_best_path_with_elephant(
-        &valves,
-        closed_valves.clone(),
-        "AA",
-        "AA",
-        &paths,
-        0,
-        0,
-        26,
-        26,
-        0,
-    );
-
-    println!("\nPart 2: {}", result); // 2705 is the correct output.
+    #[test]
+    fn records_best_pressure_per_set() {
+        let valves = parse_valves(SAMPLE_INPUT).unwrap();
+        let graph = ValveGraph::new(&valves).unwrap().compress("AA").unwrap();
+        let best = best_pressures(&graph, 0, 30);
+        // Only the six valves with flow take up bits, so AA doesn't.
+        assert_eq!(graph.openable_valves(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(best.len(), 1 << 6);
+        // Only opening DD: 1 minute to walk there and 1 to open it leaves 28 minutes of flow.
+        assert_eq!(best[1 << 2], 28 * 20);
+        assert_eq!(best[0], 0);
+    }
+
+    #[test]
+    fn finds_max_pressure_in_input() {
+        let valves = parse_valves(include_str!("day16.txt")).unwrap();
+        let graph = ValveGraph::new(&valves).unwrap().compress("AA").unwrap();
+        assert_eq!(max_pressure(&graph, 0, 30), 1991);
+        assert_eq!(max_pressure_with_elephant(&graph, 0, 26), 2705);
+    }
+
+    #[test]
+    fn plans_for_one_agent() {
+        let valves = parse_valves(SAMPLE_INPUT).unwrap();
+        let graph = ValveGraph::new(&valves).unwrap();
+        let agents = ["AA:30".parse::<Agent>().unwrap()];
+        let plan = plan_agents(&graph, &agents).unwrap();
+        let schedule = [
+            (2, "DD"),
+            (5, "BB"),
+            (9, "JJ"),
+            (17, "HH"),
+            (21, "EE"),
+            (24, "CC"),
+        ];
+        assert_eq!(
+            plan,
+            Plan {
+                pressure: 1651,
+                schedules: vec![schedule
+                    .iter()
+                    .map(|(minute, valve)| (*minute, valve.to_string()))
+                    .collect()],
+            }
+        );
+    }
+
+    #[test]
+    fn plans_for_many_agents() {
+        let valves = parse_valves(SAMPLE_INPUT).unwrap();
+        let graph = ValveGraph::new(&valves).unwrap();
+        let agent = |s: &str| s.parse::<Agent>().unwrap();
+
+        let plan = plan_agents(&graph, &[agent("AA:26"), agent("AA:26")]).unwrap();
+        assert_eq!(plan.pressure, 1707);
+        assert_eq!(plan.schedules.len(), 2);
+
+        // Every valve is opened by at most one agent, and the schedules add up to the total.
+        let opened = plan.schedules.iter().flatten().collect::<Vec<_>>();
+        let pressure = opened
+            .iter()
+            .map(|(minute, valve)| {
+                let valve = graph.index_of(valve).unwrap();
+                (26 - minute) * graph.flow_rates[valve]
+            })
+            .sum::<u32>();
+        assert_eq!(pressure, plan.pressure);
+        let valves = opened
+            .iter()
+            .map(|(_, valve)| valve)
+            .collect::<HashSet<_>>();
+        assert_eq!(valves.len(), opened.len());
+
+        // Agents can start in different places and have different time budgets.
+        let plan = plan_agents(&graph, &[agent("JJ:3"), agent("HH:2"), agent("AA:0")]).unwrap();
+        assert_eq!(plan.pressure, 2 * 21 + 22);
+        assert_eq!(plan.schedules[2], vec![]);
+
+        assert!(plan_agents(&graph, &[agent("ZZ:30")]).is_none());
+    }
 }
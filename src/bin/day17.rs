@@ -53,6 +53,7 @@ impl<'a, T> CircularIterator<'a, T> {
         result
     }
 
+    #[allow(dead_code)]
     pub fn reset(&mut self) {
         self.current_index = 0;
     }
@@ -196,6 +197,7 @@ impl Chamber {
     }
 }
 
+#[allow(dead_code)]
 fn print(chamber: &Chamber, rock: &Rock, movement: char) {
     // print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
     print!("{esc}[1;1H", esc = 27 as char);
@@ -254,8 +256,8 @@ fn main() {
 
             // Can the rock be pushed by the jet?
             let x = match jet {
-                '>' => rock.x as isize + 1,
-                '<' => rock.x as isize - 1,
+                '>' => rock.x + 1,
+                '<' => rock.x - 1,
                 _ => panic!("Invalid movement"),
             };
             let next_rock = Rock::new(rock.shape, x, rock.y);
@@ -273,7 +275,7 @@ fn main() {
             // print(&chamber, &rock, arrow);
 
             // Can the rock fall down?
-            let y = rock.y as isize - 1;
+            let y = rock.y - 1;
             let next_rock = Rock::new(rock.shape, rock.x, y);
             if chamber.collides(&next_rock) {
                 chamber.add_rock(&rock);
@@ -29,8 +29,7 @@ impl Stacks {
             temp.push(e);
         }
 
-        while !temp.is_empty() {
-            let e = temp.pop().unwrap();
+        while let Some(e) = temp.pop() {
             self.0[mv.to as usize - 1].push(e);
         }
     }
@@ -52,11 +51,7 @@ pub fn parse_input(input: &str) -> Result<(Stacks, Vec<Move>), InputParseError>
 
     let mut lines = input.lines();
     // Parse initial stack.
-    loop {
-        let Some(line) = lines.next() else {
-            break;
-        };
-
+    for line in lines.by_ref() {
         // Empty line marks the end of the stacks section.
         if line.trim().is_empty() {
             break;
@@ -1,5 +1,6 @@
 use std::{collections::HashMap, str::FromStr};
 
+#[allow(dead_code)]
 const SAMPLE_INPUT: &str = r"$ cd /
 $ ls
 dir a
@@ -24,6 +25,7 @@ $ ls
 5626152 d.ext
 7214296 k";
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct InputParseError(String);
 
@@ -73,6 +75,7 @@ impl FromStr for Entry {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum Node {
     Directory(String, HashMap<String, Node>),
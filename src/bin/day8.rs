@@ -1,13 +1,35 @@
-use std::str::FromStr;
+use std::{fs, str::FromStr};
 
+#[allow(dead_code)]
 const SAMPLE_INPUT: &str = r"30373
 25512
 65332
 33549
 35390";
 
+// Characters used to draw the scenic score heatmap, from lowest to highest score.
+const HEATMAP_RAMP: &[u8] = b" .:-=+*#%@";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heatmap {
+    Visibility,
+    ScenicScore,
+}
+
+// A candidate location for the tree house, with the viewing distance in each direction.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TreeHouseSite {
+    pub row: usize,
+    pub col: usize,
+    pub scenic_score: usize,
+    pub north: usize,
+    pub south: usize,
+    pub east: usize,
+    pub west: usize,
+}
+
 #[derive(Debug)]
-struct TreeGrid {
+pub struct TreeGrid {
     pub width: usize,
     pub height: usize,
     pub tree_heights: Vec<usize>,
@@ -135,16 +157,9 @@ impl TreeGrid {
         }
 
         let main_tree_height = self.height_at(row, col);
-        println!("Origin Height: {}", main_tree_height);
         let mut num_trees = 0;
 
         for col in (0..col).rev() {
-            println!(
-                "row: {}, col: {}, height: {}",
-                row,
-                col,
-                self.height_at(row, col)
-            );
             num_trees += 1;
             if self.height_at(row, col) >= main_tree_height {
                 break;
@@ -161,7 +176,7 @@ impl TreeGrid {
         let main_tree_height = self.height_at(row, col);
         let mut num_trees = 0;
 
-        for col in col + 1..self.height {
+        for col in col + 1..self.width {
             num_trees += 1;
             if self.height_at(row, col) >= main_tree_height {
                 break;
@@ -169,6 +184,173 @@ impl TreeGrid {
         }
         num_trees
     }
+
+    // Returns the positions in `tree_heights` for every line of sight across the grid: each row
+    // from west to east and from east to west, and each column from north to south and from south
+    // to north.
+    fn lines_of_sight(&self) -> Vec<Vec<usize>> {
+        let mut lines = Vec::with_capacity(2 * (self.width + self.height));
+        for row in 0..self.height {
+            let line = (0..self.width)
+                .map(|col| row * self.width + col)
+                .collect::<Vec<_>>();
+            lines.push(line.iter().rev().copied().collect());
+            lines.push(line);
+        }
+
+        for col in 0..self.width {
+            let line = (0..self.height)
+                .map(|row| row * self.width + col)
+                .collect::<Vec<_>>();
+            lines.push(line.iter().rev().copied().collect());
+            lines.push(line);
+        }
+        lines
+    }
+
+    // Calculates the visibility of every tree in the grid, with a single sweep per direction. The
+    // result is indexed in the same way as `tree_heights`.
+    pub fn visibility_map(&self) -> Vec<bool> {
+        let mut visible = vec![false; self.tree_heights.len()];
+        for line in self.lines_of_sight() {
+            let mut tallest: Option<usize> = None;
+            for pos in line {
+                let height = self.tree_heights[pos];
+                match tallest {
+                    Some(tallest) if height <= tallest => {}
+                    _ => {
+                        visible[pos] = true;
+                        tallest = Some(height);
+                    }
+                }
+            }
+        }
+        visible
+    }
+
+    // Calculates the scenic score of every tree in the grid. For each line of sight, a stack keeps
+    // the trees that can still block the view of the trees further along the line, so each tree
+    // is pushed and popped at most once per direction. The result is indexed in the same way as
+    // `tree_heights`.
+    pub fn scenic_scores(&self) -> Vec<usize> {
+        let mut scores = vec![1; self.tree_heights.len()];
+        for line in self.lines_of_sight() {
+            // (index in line, height) of the trees that may still block the view.
+            let mut stack: Vec<(usize, usize)> = vec![];
+            for (i, pos) in line.into_iter().enumerate() {
+                let height = self.tree_heights[pos];
+                while let Some((_, blocking_height)) = stack.last() {
+                    if *blocking_height >= height {
+                        break;
+                    }
+                    stack.pop();
+                }
+
+                // Without a blocking tree, the view goes all the way to the edge.
+                let distance = match stack.last() {
+                    Some((blocking, _)) => i - blocking,
+                    None => i,
+                };
+                scores[pos] *= distance;
+                stack.push((i, height));
+            }
+        }
+        scores
+    }
+
+    // Returns the `k` trees with the highest scenic score, best first. Ties are broken by row and
+    // then by column.
+    pub fn best_tree_house_sites(&self, k: usize) -> Vec<TreeHouseSite> {
+        let mut positions = (0..self.tree_heights.len()).collect::<Vec<_>>();
+        let scenic_scores = self.scenic_scores();
+        positions.sort_by(|a, b| scenic_scores[*b].cmp(&scenic_scores[*a]).then(a.cmp(b)));
+
+        positions
+            .into_iter()
+            .take(k)
+            .map(|pos| {
+                let (row, col) = (pos / self.width, pos % self.width);
+                TreeHouseSite {
+                    row,
+                    col,
+                    scenic_score: scenic_scores[pos],
+                    north: self.scenic_score_north(row, col),
+                    south: self.scenic_score_south(row, col),
+                    // `scenic_score_east` walks towards column 0, so it's the view to the west,
+                    // and `scenic_score_west` is the view to the east.
+                    east: self.scenic_score_west(row, col),
+                    west: self.scenic_score_east(row, col),
+                }
+            })
+            .collect()
+    }
+
+    // Values used to draw a heatmap, scaled to 0..=255. Scenic scores are dominated by a few very
+    // high values, so they're scaled by their square root to keep the rest of the forest readable.
+    fn heatmap_levels(&self, heatmap: Heatmap) -> Vec<u8> {
+        match heatmap {
+            Heatmap::Visibility => self
+                .visibility_map()
+                .into_iter()
+                .map(|visible| if visible { 255 } else { 0 })
+                .collect(),
+            Heatmap::ScenicScore => {
+                let scores = self.scenic_scores();
+                let max = scores.iter().copied().max().unwrap_or(0).max(1) as f64;
+                scores
+                    .into_iter()
+                    .map(|score| ((score as f64 / max).sqrt() * 255.0) as u8)
+                    .collect()
+            }
+        }
+    }
+
+    // Draws the heatmap as text, one character per tree. Visibility uses '#' for visible trees
+    // and '.' for hidden ones. Scenic scores use the characters in `HEATMAP_RAMP`.
+    pub fn render_ascii(&self, heatmap: Heatmap) -> String {
+        let levels = self.heatmap_levels(heatmap);
+        let mut content = String::with_capacity((self.width + 1) * self.height);
+        for row in levels.chunks(self.width) {
+            for level in row {
+                let char = match heatmap {
+                    Heatmap::Visibility if *level > 0 => '#',
+                    Heatmap::Visibility => '.',
+                    Heatmap::ScenicScore => {
+                        let index = *level as usize * (HEATMAP_RAMP.len() - 1) / 255;
+                        HEATMAP_RAMP[index] as char
+                    }
+                };
+                content.push(char);
+            }
+            content.push('\n');
+        }
+        content
+    }
+
+    // Draws the heatmap as a plain (P3) PPM image, one pixel per tree. Visible trees are green
+    // and hidden ones dark grey. Scenic scores go from black through red to yellow.
+    pub fn render_ppm(&self, heatmap: Heatmap) -> String {
+        let mut content = format!("P3\n{} {}\n255\n", self.width, self.height);
+        for row in self.heatmap_levels(heatmap).chunks(self.width) {
+            let pixels = row
+                .iter()
+                .map(|level| {
+                    let (r, g, b) = match heatmap {
+                        Heatmap::Visibility if *level > 0 => (34, 139, 34),
+                        Heatmap::Visibility => (40, 40, 40),
+                        Heatmap::ScenicScore => {
+                            let level = *level as u32;
+                            ((level * 2).min(255), (level * 2).saturating_sub(255), 0)
+                        }
+                    };
+                    format!("{} {} {}", r, g, b)
+                })
+                .collect::<Vec<_>>();
+            content.push_str(&pixels.join(" "));
+            content.push('\n');
+        }
+        content
+    }
 }
 
 impl FromStr for TreeGrid {
@@ -195,23 +377,29 @@ impl FromStr for TreeGrid {
 fn main() {
     let tree_grid = include_str!("day8.txt").parse::<TreeGrid>().unwrap();
     // let tree_grid = SAMPLE_INPUT.parse::<TreeGrid>().unwrap();
-    let mut visible_count = 0;
-    let mut highest_scenic_score = 0;
-    for row in 0..tree_grid.height {
-        for col in 0..tree_grid.width {
-            if tree_grid.visible(row, col) {
-                visible_count += 1;
-            }
-
-            let scenic_score = tree_grid.scenic_score(row, col);
-            if scenic_score > highest_scenic_score {
-                highest_scenic_score = scenic_score;
-            }
-        }
-    }
+    let visible_count = tree_grid
+        .visibility_map()
+        .into_iter()
+        .filter(|visible| *visible)
+        .count();
+    let highest_scenic_score = tree_grid.scenic_scores().into_iter().max().unwrap_or(0);
 
     println!("Part 1: {}", visible_count);
     println!("Part 2: {}", highest_scenic_score);
+
+    println!("Best tree house sites:");
+    for site in tree_grid.best_tree_house_sites(5) {
+        println!(
+            "\t({}, {}) => score: {}, north: {}, south: {}, east: {}, west: {}",
+            site.row, site.col, site.scenic_score, site.north, site.south, site.east, site.west
+        );
+    }
+    println!("{}", tree_grid.render_ascii(Heatmap::ScenicScore));
+
+    // Optionally write the scenic score heatmap as a PPM image.
+    if let Some(path) = std::env::args().nth(1) {
+        fs::write(path, tree_grid.render_ppm(Heatmap::ScenicScore)).unwrap();
+    }
 }
 
 #[cfg(test)]
@@ -280,4 +468,86 @@ mod tests {
         assert_eq!(tree_grid.scenic_score(1, 2), 4);
         assert_eq!(tree_grid.scenic_score(3, 2), 8);
     }
+
+    #[test]
+    fn visibility_map() {
+        let tree_grid = SAMPLE_INPUT.parse::<TreeGrid>().unwrap();
+        let visibility_map = tree_grid.visibility_map();
+        for row in 0..tree_grid.height {
+            for col in 0..tree_grid.width {
+                assert_eq!(
+                    visibility_map[row * tree_grid.width + col],
+                    tree_grid.visible(row, col)
+                );
+            }
+        }
+        assert_eq!(visibility_map.iter().filter(|v| **v).count(), 21);
+    }
+
+    #[test]
+    fn scenic_scores() {
+        let tree_grid = SAMPLE_INPUT.parse::<TreeGrid>().unwrap();
+        let scenic_scores = tree_grid.scenic_scores();
+        for row in 0..tree_grid.height {
+            for col in 0..tree_grid.width {
+                assert_eq!(
+                    scenic_scores[row * tree_grid.width + col],
+                    tree_grid.scenic_score(row, col)
+                );
+            }
+        }
+        assert_eq!(scenic_scores.iter().max(), Some(&8));
+    }
+
+    #[test]
+    fn best_tree_house_sites() {
+        let tree_grid = SAMPLE_INPUT.parse::<TreeGrid>().unwrap();
+        let sites = tree_grid.best_tree_house_sites(2);
+        assert_eq!(
+            sites[0],
+            TreeHouseSite {
+                row: 3,
+                col: 2,
+                scenic_score: 8,
+                north: 2,
+                south: 1,
+                east: 2,
+                west: 2,
+            }
+        );
+        assert_eq!(
+            sites[1],
+            TreeHouseSite {
+                row: 2,
+                col: 1,
+                scenic_score: 6,
+                north: 1,
+                south: 2,
+                east: 3,
+                west: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn renders_visibility_ascii() {
+        let tree_grid = SAMPLE_INPUT.parse::<TreeGrid>().unwrap();
+        assert_eq!(
+            tree_grid.render_ascii(Heatmap::Visibility),
+            "#####\n###.#\n##.##\n#.#.#\n#####\n"
+        );
+    }
+
+    #[test]
+    fn renders_scenic_score_ppm() {
+        let tree_grid = SAMPLE_INPUT.parse::<TreeGrid>().unwrap();
+        let ppm = tree_grid.render_ppm(Heatmap::ScenicScore);
+        let mut lines = ppm.lines();
+        assert_eq!(lines.next(), Some("P3"));
+        assert_eq!(lines.next(), Some("5 5"));
+        assert_eq!(lines.next(), Some("255"));
+        // The best tree is at row 3, col 2, and gets the brightest colour.
+        assert_eq!(lines.nth(3).unwrap().split(' ').nth(6), Some("255"));
+        assert_eq!(lines.count(), 1);
+    }
 }
@@ -1,5 +1,11 @@
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    fs,
+    str::FromStr,
+};
 
+#[allow(dead_code)]
 const SAMPLE_INPUT: &str = r"R 4
 U 4
 L 3
@@ -9,13 +15,31 @@ D 1
 L 5
 R 2";
 
+#[allow(dead_code)]
+const LARGER_SAMPLE_INPUT: &str = r"R 5
+U 8
+L 8
+D 3
+R 17
+D 10
+L 25
+U 20";
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct ParseInputError(String);
+
 #[derive(Debug, Hash, PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Default)]
-struct Position {
+pub struct Position {
     x: isize,
     y: isize,
 }
 
 impl Position {
+    pub fn new(x: isize, y: isize) -> Self {
+        Self { x, y }
+    }
+
     pub fn move_amount(&mut self, x: isize, y: isize) {
         self.x += x;
         self.y += y;
@@ -46,57 +70,371 @@ impl Position {
     }
 }
 
-fn main() {
-    let mut head = Position::default();
-    let mut tail = Position::default();
-    let mut tail_positions = HashSet::<Position>::new();
-
-    println!("----==== Part 1 ====----");
-    // for line in include_str!("day9.txt").lines() {
-    for line in SAMPLE_INPUT.lines() {
-        println!("{line}");
-        let mut split = line.split(' ');
-        let direction: (isize, isize) = match split.next().unwrap() {
-            "R" => (1, 0),
-            "L" => (-1, 0),
-            "U" => (0, 1),
-            "D" => (0, -1),
-            _ => panic!("Invalid input"),
-        };
-        let amount = split.next().unwrap().parse().unwrap();
-        for _ in 0..amount {
-            head.move_amount(direction.0, direction.1);
-            let (x, y) = head.calculate_movement(&tail);
-            println!("move_tail: ({x}, {y})");
-            tail.move_amount(x, y);
-            println!("head: {:?}, tail: {:?}", head, tail);
-            tail_positions.insert(tail);
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Right,
+    Left,
+    Up,
+    Down,
+}
+
+impl Direction {
+    pub fn delta(&self) -> (isize, isize) {
+        match self {
+            Direction::Right => (1, 0),
+            Direction::Left => (-1, 0),
+            Direction::Up => (0, 1),
+            Direction::Down => (0, -1),
         }
     }
-    println!("Number tail positions: {}", tail_positions.len());
-
-    println!("----==== Part 2 ====----");
-    let mut rope = vec![Position::default(); 10];
-    let mut tail_positions = HashSet::<Position>::new();
-    // for line in include_str!("day9.txt").lines() {
-    for line in SAMPLE_INPUT.lines() {
-        let mut split = line.split(' ');
-        let (x, y) = match split.next().unwrap() {
-            "R" => (1, 0),
-            "L" => (-1, 0),
-            "U" => (0, 1),
-            "D" => (0, -1),
-            _ => panic!("Invalid input"),
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let direction = match self {
+            Direction::Right => "R",
+            Direction::Left => "L",
+            Direction::Up => "U",
+            Direction::Down => "D",
+        };
+        f.write_str(direction)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Motion {
+    pub direction: Direction,
+    pub steps: usize,
+}
+
+impl FromStr for Motion {
+    type Err = ParseInputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut split = s.trim().split(' ');
+        let direction = match split.next() {
+            Some("R") => Direction::Right,
+            Some("L") => Direction::Left,
+            Some("U") => Direction::Up,
+            Some("D") => Direction::Down,
+            _ => return Err(ParseInputError(format!("Invalid direction: {}", s))),
         };
-        let amount = split.next().unwrap().parse().unwrap();
-        for _ in 0..amount {
-            rope[0].move_amount(x, y);
-            for i in 1..rope.len() {
-                let (x, y) = rope[i - 1].calculate_movement(&rope[i]);
-                rope[i].move_amount(x, y);
+
+        let steps = split
+            .next()
+            .ok_or_else(|| ParseInputError(format!("Motion missing steps: {}", s)))?
+            .parse::<usize>()
+            .map_err(|e| ParseInputError(format!("Invalid steps in {}: {}", s, e)))?;
+
+        if split.next().is_some() {
+            return Err(ParseInputError(format!("Unexpected input: {}", s)));
+        }
+
+        Ok(Motion { direction, steps })
+    }
+}
+
+impl Display for Motion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.direction, self.steps)
+    }
+}
+
+pub fn parse_motions(input: &str) -> Result<Vec<Motion>, ParseInputError> {
+    input.lines().map(|line| line.parse::<Motion>()).collect()
+}
+
+// The area of the grid drawn by `Rope::render`, inclusive on both ends.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Viewport {
+    pub min: Position,
+    pub max: Position,
+}
+
+impl Viewport {
+    pub fn new(min: Position, max: Position) -> Self {
+        Self { min, max }
+    }
+
+    // Smallest viewport containing every position the head goes through, starting from the
+    // origin. Knots only ever move towards the knot before them, so they stay inside it too.
+    pub fn from_motions(motions: &[Motion]) -> Self {
+        let mut head = Position::default();
+        let mut viewport = Viewport::new(head, head);
+        for motion in motions {
+            let (x, y) = motion.direction.delta();
+            let steps = motion.steps as isize;
+            head.move_amount(x * steps, y * steps);
+            viewport.min = Position::new(viewport.min.x.min(head.x), viewport.min.y.min(head.y));
+            viewport.max = Position::new(viewport.max.x.max(head.x), viewport.max.y.max(head.y));
+        }
+        viewport
+    }
+}
+
+// A rope made of knots, where the first knot is the head and each following knot follows the one
+// before it. Keeps track of every position visited by one of the knots.
+#[derive(Debug)]
+pub struct Rope {
+    pub knots: Vec<Position>,
+    tracked_knot: usize,
+    visited: HashSet<Position>,
+    trail: Vec<Position>,
+}
+
+impl Rope {
+    // Creates a rope with `num_knots` knots, all starting at the origin, tracking the positions
+    // visited by the knot at index `tracked_knot`.
+    pub fn new(num_knots: usize, tracked_knot: usize) -> Self {
+        if tracked_knot >= num_knots {
+            panic!("Tracked knot must be one of the rope knots");
+        }
+
+        let knots = vec![Position::default(); num_knots];
+        let visited = HashSet::from([knots[tracked_knot]]);
+        let trail = vec![knots[tracked_knot]];
+        Self {
+            knots,
+            tracked_knot,
+            visited,
+            trail,
+        }
+    }
+
+    // Creates a rope with `num_knots` knots that tracks the positions visited by the tail.
+    pub fn with_tail_tracked(num_knots: usize) -> Self {
+        Self::new(num_knots, num_knots - 1)
+    }
+
+    pub fn head(&self) -> Position {
+        self.knots[0]
+    }
+
+    pub fn tail(&self) -> Position {
+        self.knots[self.knots.len() - 1]
+    }
+
+    // Moves the head one step in the direction, and lets the other knots follow it.
+    pub fn step(&mut self, direction: Direction) {
+        let (x, y) = direction.delta();
+        self.knots[0].move_amount(x, y);
+        for i in 1..self.knots.len() {
+            let (x, y) = self.knots[i - 1].calculate_movement(&self.knots[i]);
+            self.knots[i].move_amount(x, y);
+        }
+
+        let tracked = self.knots[self.tracked_knot];
+        self.visited.insert(tracked);
+        if self.trail.last() != Some(&tracked) {
+            self.trail.push(tracked);
+        }
+    }
+
+    pub fn apply(&mut self, motion: &Motion) {
+        for _ in 0..motion.steps {
+            self.step(motion.direction);
+        }
+    }
+
+    pub fn visited(&self) -> &HashSet<Position> {
+        &self.visited
+    }
+
+    // Positions of the tracked knot, in the order it moved through them.
+    pub fn trail(&self) -> &[Position] {
+        &self.trail
+    }
+
+    // Draws the rope using the puzzle notation: 'H' for the head, the knot index for the other
+    // knots and 's' for the starting position. Knots closer to the head are drawn on top.
+    pub fn render(&self, viewport: &Viewport) -> String {
+        let mut content = String::new();
+        for y in (viewport.min.y..=viewport.max.y).rev() {
+            for x in viewport.min.x..=viewport.max.x {
+                let position = Position::new(x, y);
+                let char = match self.knots.iter().position(|knot| *knot == position) {
+                    Some(0) => 'H',
+                    Some(i) => char::from_digit(i as u32, 36).unwrap_or('#'),
+                    None if position == Position::default() => 's',
+                    None => '.',
+                };
+                content.push(char);
             }
-            tail_positions.insert(rope[rope.len() - 1]);
+            content.push('\n');
+        }
+        content
+    }
+
+    // Exports the trail of the tracked knot as an SVG path. The y axis is flipped, so up in the
+    // puzzle is also up in the image.
+    pub fn trail_svg(&self) -> String {
+        let min_x = self.trail.iter().map(|p| p.x).min().unwrap_or(0);
+        let max_x = self.trail.iter().map(|p| p.x).max().unwrap_or(0);
+        let min_y = self.trail.iter().map(|p| p.y).min().unwrap_or(0);
+        let max_y = self.trail.iter().map(|p| p.y).max().unwrap_or(0);
+
+        let path = self
+            .trail
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("{}{} {}", if i == 0 { 'M' } else { 'L' }, p.x, -p.y))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n\
+             <path d=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"0.2\"/>\n\
+             </svg>\n",
+            min_x - 1,
+            -max_y - 1,
+            max_x - min_x + 2,
+            max_y - min_y + 2,
+            path
+        )
+    }
+}
+
+fn main() {
+    // let motions = parse_motions(SAMPLE_INPUT).unwrap();
+    let motions = parse_motions(include_str!("day9.txt")).unwrap();
+
+    let mut rope = Rope::with_tail_tracked(2);
+    motions.iter().for_each(|motion| rope.apply(motion));
+    println!("Part 1: {}", rope.visited().len());
+
+    // Pass "--frames" to draw the rope after each motion, and "--svg <path>" to write the tail
+    // trail to a file.
+    let args = std::env::args().collect::<Vec<_>>();
+    let print_frames = args.iter().any(|arg| arg == "--frames");
+    let viewport = Viewport::from_motions(&motions);
+
+    let mut rope = Rope::with_tail_tracked(10);
+    for motion in &motions {
+        rope.apply(motion);
+        if print_frames {
+            println!("== {} ==\n\n{}", motion, rope.render(&viewport));
+        }
+    }
+    println!("Part 2: {}", rope.visited().len());
+
+    if let Some(i) = args.iter().position(|arg| arg == "--svg") {
+        let path = args.get(i + 1).expect("--svg requires an output path");
+        fs::write(path, rope.trail_svg()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visited_by(input: &str, num_knots: usize, tracked_knot: usize) -> usize {
+        let mut rope = Rope::new(num_knots, tracked_knot);
+        for motion in parse_motions(input).unwrap() {
+            rope.apply(&motion);
         }
+        rope.visited().len()
+    }
+
+    #[test]
+    fn parses_motion() {
+        let motion = "U 14".parse::<Motion>().unwrap();
+        assert_eq!(
+            motion,
+            Motion {
+                direction: Direction::Up,
+                steps: 14
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_motions() {
+        assert!("X 4".parse::<Motion>().is_err());
+        assert!("R".parse::<Motion>().is_err());
+        assert!("R four".parse::<Motion>().is_err());
+        assert!("R 4 4".parse::<Motion>().is_err());
+    }
+
+    #[test]
+    fn two_knots() {
+        assert_eq!(visited_by(SAMPLE_INPUT, 2, 1), 13);
+    }
+
+    #[test]
+    fn ten_knots() {
+        assert_eq!(visited_by(SAMPLE_INPUT, 10, 9), 1);
+        assert_eq!(visited_by(LARGER_SAMPLE_INPUT, 10, 9), 36);
+    }
+
+    #[test]
+    fn displays_motion() {
+        let motion = "L 25".parse::<Motion>().unwrap();
+        assert_eq!(motion.to_string(), "L 25");
+    }
+
+    #[test]
+    fn viewport_from_motions() {
+        let motions = parse_motions(SAMPLE_INPUT).unwrap();
+        assert_eq!(
+            Viewport::from_motions(&motions),
+            Viewport::new(Position::new(0, 0), Position::new(5, 4))
+        );
+    }
+
+    #[test]
+    fn renders_rope() {
+        let motions = parse_motions(SAMPLE_INPUT).unwrap();
+        let viewport = Viewport::from_motions(&motions);
+        let mut rope = Rope::with_tail_tracked(10);
+
+        rope.apply(&motions[0]);
+        assert_eq!(
+            rope.render(&viewport),
+            "......\n......\n......\n......\n4321H.\n"
+        );
+
+        rope.apply(&motions[1]);
+        assert_eq!(
+            rope.render(&viewport),
+            "....H.\n....1.\n..432.\n.5....\n6.....\n"
+        );
+    }
+
+    #[test]
+    fn renders_start() {
+        let mut rope = Rope::with_tail_tracked(2);
+        rope.apply(&"R 3".parse().unwrap());
+        let viewport = Viewport::new(Position::new(0, 0), Position::new(3, 0));
+        assert_eq!(rope.render(&viewport), "s.1H\n");
+    }
+
+    #[test]
+    fn exports_trail_svg() {
+        let mut rope = Rope::with_tail_tracked(2);
+        rope.apply(&"R 3".parse().unwrap());
+        rope.apply(&"U 2".parse().unwrap());
+        assert_eq!(
+            rope.trail(),
+            &[
+                Position::new(0, 0),
+                Position::new(1, 0),
+                Position::new(2, 0),
+                Position::new(3, 1)
+            ]
+        );
+        let svg = rope.trail_svg();
+        assert!(svg.contains("viewBox=\"-1 -2 5 3\""));
+        assert!(svg.contains("d=\"M0 0 L1 0 L2 0 L3 -1\""));
+    }
+
+    #[test]
+    fn tracks_head() {
+        // The head visits the origin plus every step of every motion, minus the positions it
+        // revisits.
+        let mut rope = Rope::new(3, 0);
+        rope.apply(&"R 2".parse().unwrap());
+        rope.apply(&"L 2".parse().unwrap());
+        assert_eq!(rope.visited().len(), 3);
+        assert_eq!(rope.head(), Position::default());
     }
-    println!("Number tail positions: {}", tail_positions.len());
 }